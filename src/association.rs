@@ -0,0 +1,213 @@
+use crate::errors::{CrackerError, Result};
+use crate::parser::ParsedHash;
+use std::collections::HashSet;
+
+/// A hash to crack together with the account data that came with it
+#[derive(Debug, Clone)]
+pub struct HashTarget {
+    /// Name shown in results: the first field, or the line number
+    pub label: String,
+    pub hash: ParsedHash,
+    /// Fields preceding the hash (username, email, company, ...)
+    pub fields: Vec<String>,
+}
+
+impl HashTarget {
+    /// Parse a hash file line: optional `:`-separated fields, then the hash
+    ///
+    /// Example: `jsmith:john.smith@acme.com:Acme Corp:pbkdf2:sha256:600000$salt$digest`
+    pub fn parse(line: &str, line_number: usize) -> Result<Self> {
+        let start = line.find("pbkdf2:").ok_or_else(|| {
+            CrackerError::InvalidHashFormat(format!("Line {}: no pbkdf2 hash found", line_number))
+        })?;
+        let hash = ParsedHash::parse(line[start..].trim())?;
+        let fields: Vec<String> = line[..start]
+            .split(':')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(String::from)
+            .collect();
+        let label = fields
+            .first()
+            .cloned()
+            .unwrap_or_else(|| format!("line {}", line_number));
+
+        Ok(Self {
+            label,
+            hash,
+            fields,
+        })
+    }
+
+    /// Load every hash from a hash file, skipping blank lines and `#` comments
+    pub fn load_file(path: &str) -> Result<Vec<Self>> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            CrackerError::InvalidHashFormat(format!("Failed to read hash file {}: {}", path, e))
+        })?;
+
+        let mut targets = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            targets.push(Self::parse(line, index + 1)?);
+        }
+
+        if targets.is_empty() {
+            return Err(CrackerError::InvalidHashFormat(format!(
+                "No hashes in {}",
+                path
+            )));
+        }
+        Ok(targets)
+    }
+}
+
+/// Build base words from an account's fields and the application's domain
+///
+/// Email addresses contribute their local part and their domain; other
+/// fields are used as names. Names yield the value itself, its lowercase
+/// form, its letter and digit runs, the runs joined together, and initial
+/// plus last name (`john.smith` -> `jsmith`). Domains yield their labels
+/// without the TLD or `www`. Words are unique and keep their first position.
+pub fn derive_words(fields: &[String], domain: Option<&str>) -> Vec<Vec<u8>> {
+    let mut words = WordSet::default();
+    for field in fields {
+        match field.split_once('@') {
+            Some((local, email_domain)) => {
+                add_name(&mut words, local);
+                add_domain(&mut words, email_domain);
+            }
+            None => add_name(&mut words, field),
+        }
+    }
+    if let Some(domain) = domain {
+        add_domain(&mut words, domain);
+    }
+    words.words
+}
+
+#[derive(Default)]
+struct WordSet {
+    seen: HashSet<String>,
+    words: Vec<Vec<u8>>,
+}
+
+impl WordSet {
+    fn add(&mut self, word: &str) {
+        if !word.is_empty() && self.seen.insert(word.to_string()) {
+            self.words.push(word.as_bytes().to_vec());
+        }
+    }
+}
+
+fn add_name(words: &mut WordSet, name: &str) {
+    let name = name.trim();
+    words.add(name);
+    words.add(&name.to_lowercase());
+
+    // Split into runs of letters and runs of digits
+    let mut runs: Vec<String> = Vec::new();
+    let mut previous: Option<bool> = None;
+    for c in name.chars() {
+        let is_digit = c.is_numeric();
+        if !c.is_alphanumeric() {
+            previous = None;
+            continue;
+        }
+        match runs.last_mut() {
+            Some(run) if previous == Some(is_digit) => run.extend(c.to_lowercase()),
+            _ => runs.push(c.to_lowercase().collect()),
+        }
+        previous = Some(is_digit);
+    }
+    if runs.len() < 2 {
+        return;
+    }
+
+    for run in &runs {
+        words.add(run);
+    }
+    words.add(&runs.concat());
+
+    let letters: Vec<&String> = runs
+        .iter()
+        .filter(|run| run.chars().all(char::is_alphabetic))
+        .collect();
+    if letters.len() >= 2 {
+        let joined: String = letters.iter().map(|run| run.as_str()).collect();
+        words.add(&joined);
+        let first = letters[0].chars().next().unwrap_or_default();
+        words.add(&format!("{}{}", first, letters[letters.len() - 1]));
+    }
+}
+
+fn add_domain(words: &mut WordSet, domain: &str) {
+    let labels: Vec<&str> = domain.trim().trim_end_matches('.').split('.').collect();
+    let labels = match labels.len() {
+        0 | 1 => &labels[..],
+        n => &labels[..n - 1],
+    };
+    for label in labels {
+        if !label.eq_ignore_ascii_case("www") {
+            add_name(words, label);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "pbkdf2:sha256:1000$saltsalt$811f75d2c9f52307072992d0681f258b1366ae07d82d31cbd5066e5a65874f94";
+
+    fn texts(words: Vec<Vec<u8>>) -> Vec<String> {
+        words
+            .into_iter()
+            .map(|w| String::from_utf8(w).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_hash_lines() {
+        let target = HashTarget::parse(&format!("jsmith:john@acme.com::Acme:{}", HASH), 1).unwrap();
+        assert_eq!(target.label, "jsmith");
+        assert_eq!(target.fields, vec!["jsmith", "john@acme.com", "Acme"]);
+        assert_eq!(target.hash.iterations, 1000);
+
+        let bare = HashTarget::parse(HASH, 7).unwrap();
+        assert_eq!(bare.label, "line 7");
+        assert!(bare.fields.is_empty());
+
+        assert!(HashTarget::parse("jsmith:nothing here", 1).is_err());
+    }
+
+    #[test]
+    fn test_derive_words() {
+        let fields = vec![
+            "JSmith1985".to_string(),
+            "john.smith@mail.acme-corp.com".to_string(),
+        ];
+        assert_eq!(
+            texts(derive_words(&fields, Some("www.example.org"))),
+            vec![
+                "JSmith1985",
+                "jsmith1985",
+                "jsmith",
+                "1985",
+                "john.smith",
+                "john",
+                "smith",
+                "johnsmith",
+                "mail",
+                "acme-corp",
+                "acme",
+                "corp",
+                "acmecorp",
+                "acorp",
+                "example",
+            ]
+        );
+    }
+}
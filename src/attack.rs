@@ -0,0 +1,654 @@
+use crate::association::HashTarget;
+use crate::cracker::Pbkdf2Cracker;
+use crate::dedup::{dedup_in_place, BloomFilter};
+use crate::encoding::format_candidate;
+use crate::errors::Result;
+use crate::filter::CandidateFilter;
+use crate::rules::{write_rules_file, RuleEngine, RuleHits};
+use crate::say;
+use crate::stats::CrackingStats;
+use crate::wordlist::WordEntry;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Base words expanded and tested together
+pub const CHUNK_SIZE: usize = 1000;
+
+/// A password candidate and the base word and rule that produced it
+pub struct Candidate<'a> {
+    pub base: &'a [u8],
+    /// Index of the base word in its chunk
+    pub word: usize,
+    pub rule_index: usize,
+    pub password: Vec<u8>,
+}
+
+/// A cracked password and how it was produced
+pub struct Cracked {
+    pub base: Vec<u8>,
+    pub rule_index: usize,
+    pub password: Vec<u8>,
+}
+
+/// Turns base words into the candidates that actually need hashing
+pub struct CandidatePipeline {
+    pub rule_engine: RuleEngine,
+    filter: CandidateFilter,
+    global_dedup: Option<BloomFilter>,
+    pub hits: RuleHits,
+    skipped: AtomicU64,
+    duplicates: AtomicU64,
+}
+
+impl CandidatePipeline {
+    pub fn new(
+        rule_engine: RuleEngine,
+        filter: CandidateFilter,
+        global_dedup: Option<BloomFilter>,
+    ) -> Self {
+        Self {
+            hits: RuleHits::new(rule_engine.count()),
+            rule_engine,
+            filter,
+            global_dedup,
+            skipped: AtomicU64::new(0),
+            duplicates: AtomicU64::new(0),
+        }
+    }
+
+    /// Expand a chunk of base words into candidates, in word then rule order
+    /// `global_dedup` records them in (and filters them by) the run-wide filter
+    pub fn expand<'a>(&self, chunk: &'a [WordEntry], global_dedup: bool) -> Vec<Candidate<'a>> {
+        let mut candidates = Vec::new();
+
+        for (index, WordEntry { word, .. }) in chunk.iter().enumerate() {
+            let mut generated = self.rule_engine.generate_candidates_with_rules(word);
+
+            // Identical candidates from different rules cost a full derivation each
+            let mut duplicates = dedup_in_place(&mut generated, |(_, c)| c.as_slice()) as u64;
+
+            // Drop candidates the target's password policy would never accept
+            if self.filter.is_active() {
+                let before = generated.len();
+                generated.retain(|(_, candidate)| self.filter.allows(candidate));
+                self.skipped
+                    .fetch_add((before - generated.len()) as u64, Ordering::Relaxed);
+            }
+
+            if let Some(bloom) = self.global_dedup.as_ref().filter(|_| global_dedup) {
+                let before = generated.len();
+                generated.retain(|(_, candidate)| !bloom.check_and_insert(candidate));
+                duplicates += (before - generated.len()) as u64;
+            }
+
+            self.duplicates.fetch_add(duplicates, Ordering::Relaxed);
+            candidates.extend(
+                generated
+                    .into_iter()
+                    .map(|(rule_index, password)| Candidate {
+                        base: word,
+                        word: index,
+                        rule_index,
+                        password,
+                    }),
+            );
+        }
+
+        candidates
+    }
+
+    /// Print how many derivations were avoided by filtering and dedup
+    pub fn print_savings(&self) {
+        if self.filter.is_active() {
+            say!(
+                "   Skipped by policy: {}",
+                self.skipped.load(Ordering::Relaxed)
+            );
+        }
+        say!(
+            "   Duplicates skipped: {} (PBKDF2 derivations saved)",
+            self.duplicates.load(Ordering::Relaxed)
+        );
+    }
+
+    /// Write the rules that cracked at least one password to a rules file
+    pub fn save_hit_rules(&self, path: &str) -> Result<()> {
+        let hit_rules = self.hits.hit_rules(&self.rule_engine);
+        write_rules_file(path, hit_rules.iter().map(|(rule, _)| *rule))?;
+        say!("\n💾 Saved {} hit rules to {}", hit_rules.len(), path);
+        Ok(())
+    }
+}
+
+/// A hash being cracked and its result
+pub struct Target {
+    pub label: String,
+    cracker: Pbkdf2Cracker,
+    pub cracked: Mutex<Option<Cracked>>,
+    done: AtomicBool,
+}
+
+/// Every hash of the session; candidates are only tested against hashes
+/// that are still uncracked
+pub struct Targets {
+    pub targets: Vec<Target>,
+    remaining: AtomicUsize,
+}
+
+impl Targets {
+    pub fn new(hashes: &[HashTarget]) -> Self {
+        let targets = hashes
+            .iter()
+            .map(|target| Target {
+                label: target.label.clone(),
+                cracker: Pbkdf2Cracker::new(target.hash.clone()),
+                cracked: Mutex::new(None),
+                done: AtomicBool::new(false),
+            })
+            .collect();
+        Self {
+            targets,
+            remaining: AtomicUsize::new(hashes.len()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    pub fn cracked_count(&self) -> usize {
+        self.len() - self.remaining.load(Ordering::Relaxed)
+    }
+
+    pub fn all_cracked(&self) -> bool {
+        self.remaining.load(Ordering::Relaxed) == 0
+    }
+
+    pub fn is_cracked(&self, index: usize) -> bool {
+        self.targets[index].done.load(Ordering::Relaxed)
+    }
+
+    /// Test a candidate against one hash, recording it if it matches
+    fn try_crack(
+        &self,
+        index: usize,
+        candidate: &Candidate,
+        hits: &RuleHits,
+        stats: &CrackingStats,
+    ) -> bool {
+        let target = &self.targets[index];
+        if target.done.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        stats.increment(1);
+        if !target.cracker.test_password(&candidate.password) {
+            return false;
+        }
+
+        // Another thread may have matched the same hash in the meantime
+        if target.done.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        self.remaining.fetch_sub(1, Ordering::SeqCst);
+        hits.record(candidate.rule_index);
+        *target.cracked.lock().unwrap() = Some(Cracked {
+            base: candidate.base.to_vec(),
+            rule_index: candidate.rule_index,
+            password: candidate.password.clone(),
+        });
+        true
+    }
+}
+
+/// Where to resume a source: the first (word, rule) pair not yet tested
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: u64,
+    pub byte_offset: u64,
+    pub rule_index: usize,
+}
+
+impl Position {
+    fn at(entry: &WordEntry, rule_index: usize) -> Self {
+        Self {
+            line: entry.line,
+            byte_offset: entry.byte_offset,
+            rule_index,
+        }
+    }
+}
+
+/// Tests words against every target, tracking how far testing is complete
+///
+/// Candidates are tested in parallel and may finish out of order, so the
+/// position reported is a low-water mark: every candidate before it has
+/// been tested, some after it may have been too.
+pub struct Attack {
+    pub pipeline: CandidatePipeline,
+    pub targets: Targets,
+    stats: CrackingStats,
+    stop: Arc<AtomicBool>,
+    chunk_size: usize,
+    batch_size: usize,
+}
+
+impl Attack {
+    pub fn new(pipeline: CandidatePipeline, targets: Targets) -> Self {
+        Self {
+            pipeline,
+            targets,
+            stats: CrackingStats::new(None),
+            stop: Arc::new(AtomicBool::new(false)),
+            chunk_size: CHUNK_SIZE,
+            batch_size: rayon::current_num_threads() * 4,
+        }
+    }
+
+    /// Show a progress bar over `keyspace` (word, rule) pairs
+    pub fn with_keyspace(mut self, keyspace: u64) -> Self {
+        self.stats = CrackingStats::new(Some(keyspace));
+        self
+    }
+
+    /// Base words per chunk (`CHUNK_SIZE` by default)
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Candidates tested between progress reports (four per thread by default)
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Hash derivations so far
+    pub fn attempts(&self) -> u64 {
+        self.stats.attempts()
+    }
+
+    /// Attempt counter and timing of this attack
+    pub fn stats(&self) -> &CrackingStats {
+        &self.stats
+    }
+
+    /// Ask running work to stop after the candidates in flight
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// The flag `stop` sets, for signal handlers
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Test words derived from one hash's account data against that hash only
+    /// Kept out of the global dedup filter: these words are not tested
+    /// against the other hashes.
+    pub fn test_account_words(&self, index: usize, chunk: &[WordEntry]) {
+        let candidates = self.pipeline.expand(chunk, false);
+        self.test_candidates(&candidates, Some(index));
+    }
+
+    /// Test every word of a source, starting at rule `first_rule` of the first word
+    ///
+    /// `on_progress` is called with the resume position after each batch of
+    /// candidates, so even a chunk that takes hours reports progress.
+    /// Returns the resume position if work stopped early (everything
+    /// cracked, or `stop` was called), or `None` once the source is exhausted.
+    pub fn run<'w>(
+        &self,
+        words: impl Iterator<Item = Result<WordEntry<'w>>>,
+        first_rule: usize,
+        mut on_progress: impl FnMut(&Self, Position),
+    ) -> Result<Option<Position>> {
+        let mut words = words.peekable();
+        let mut first_rule = first_rule;
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+        let mut next_line = None;
+
+        loop {
+            if self.is_stopped() || self.targets.all_cracked() {
+                let next = words.next().transpose()?;
+                return Ok(next.map(|entry| Position::at(&entry, first_rule)));
+            }
+
+            chunk.clear();
+            while chunk.len() < self.chunk_size {
+                match words.next() {
+                    Some(entry) => chunk.push(entry?),
+                    None => break,
+                }
+            }
+            if chunk.is_empty() {
+                return Ok(None);
+            }
+
+            self.stats
+                .set_message(format_candidate(&chunk[0].word).into_owned());
+            let rules = self.pipeline.rule_engine.count();
+            let mut candidates = self.pipeline.expand(&chunk, true);
+            let skipped = first_rule;
+            if first_rule > 0 {
+                // The resumed word's earlier rules were tested before the checkpoint
+                candidates.retain(|c| c.word > 0 || c.rule_index >= first_rule);
+                first_rule = 0;
+            }
+
+            // The bar counts (word, rule) pairs, however many candidates each
+            // pair expands to; pairs filtered out or deduplicated count as done,
+            // and so do the pairs of lines the reader skipped
+            let base_line = next_line.unwrap_or(chunk[0].line);
+            let last_line = chunk[chunk.len() - 1].line;
+            let line_pairs = |line: u64| (line - base_line) as usize * rules;
+            let mut pairs_done = 0;
+            let mut advance_to = |candidate: Option<&Candidate>| {
+                let pairs = match candidate {
+                    Some(c) => line_pairs(chunk[c.word].line) + c.rule_index - skipped,
+                    None => line_pairs(last_line + 1) - skipped,
+                };
+                self.stats.advance((pairs - pairs_done) as u64);
+                pairs_done = pairs;
+            };
+
+            // Test in batches; after each, the first untested candidate's
+            // word and rule is where to resume
+            let mut start = 0;
+            while start < candidates.len() {
+                let end = (start + self.batch_size).min(candidates.len());
+                let tested = start + self.test_candidates(&candidates[start..end], None);
+                if tested < end {
+                    let untested = &candidates[tested];
+                    advance_to(Some(untested));
+                    return Ok(Some(Position::at(
+                        &chunk[untested.word],
+                        untested.rule_index,
+                    )));
+                }
+                if let Some(next) = candidates.get(end) {
+                    advance_to(Some(next));
+                    on_progress(self, Position::at(&chunk[next.word], next.rule_index));
+                }
+                start = end;
+            }
+            advance_to(None);
+            next_line = Some(last_line + 1);
+
+            // The chunk is complete: resume at the next word
+            match words.peek() {
+                Some(Ok(next)) => on_progress(self, Position::at(next, 0)),
+                Some(Err(_)) => {}
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Test candidates against one hash (`only`) or every uncracked hash
+    /// Returns how many leading candidates were fully tested.
+    fn test_candidates(&self, candidates: &[Candidate], only: Option<usize>) -> usize {
+        let targets = &self.targets;
+        let hits = &self.pipeline.hits;
+        let tested: Vec<AtomicBool> = candidates.iter().map(|_| AtomicBool::new(false)).collect();
+
+        // Test in parallel
+        candidates
+            .par_iter()
+            .zip(tested.par_iter())
+            .any(|(candidate, tested)| {
+                if self.is_stopped() {
+                    return true;
+                }
+                let finished = match only {
+                    Some(index) => {
+                        targets.try_crack(index, candidate, hits, &self.stats);
+                        targets.is_cracked(index)
+                    }
+                    None => {
+                        for index in 0..targets.len() {
+                            targets.try_crack(index, candidate, hits, &self.stats);
+                        }
+                        targets.all_cracked()
+                    }
+                };
+                tested.store(true, Ordering::Relaxed);
+                if only.is_none() && candidate.rule_index == 0 {
+                    self.stats
+                        .set_message(format_candidate(candidate.base).into_owned());
+                }
+                finished
+            });
+
+        tested
+            .iter()
+            .position(|tested| !tested.load(Ordering::Relaxed))
+            .unwrap_or(candidates.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParsedHash;
+    use crate::rules::Rule;
+    use pbkdf2::pbkdf2_hmac;
+    use sha2::Sha256;
+    use std::borrow::Cow;
+
+    const WORDS: u64 = 100;
+    const RULES: u64 = 3;
+
+    fn target(password: &str) -> HashTarget {
+        let salt = b"saltsalt".to_vec();
+        let mut digest = vec![0u8; 32];
+        pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, 20, &mut digest);
+        HashTarget {
+            label: password.to_string(),
+            hash: ParsedHash {
+                iterations: 20,
+                salt,
+                digest,
+            },
+            fields: Vec::new(),
+        }
+    }
+
+    /// Three hashes spread over the keyspace: word03, word50 + "1", reversed word99
+    fn attack() -> Attack {
+        let mut rules = RuleEngine::new();
+        rules.add_rules(vec![Rule::AppendDigit(1), Rule::Reverse]);
+        let pipeline =
+            CandidatePipeline::new(rules, CandidateFilter::new(None, None, Vec::new()), None);
+        let targets = [target("word03"), target("word501"), target("99drow")];
+        Attack::new(pipeline, Targets::new(&targets)).with_chunk_size(8)
+    }
+
+    fn words(from: u64) -> impl Iterator<Item = Result<WordEntry<'static>>> {
+        (from..WORDS).map(|line| {
+            Ok(WordEntry {
+                line,
+                byte_offset: line * 7,
+                word: Cow::Owned(format!("word{:02}", line).into_bytes()),
+            })
+        })
+    }
+
+    fn cracked(attack: &Attack) -> Vec<String> {
+        attack
+            .targets
+            .targets
+            .iter()
+            .filter(|target| target.cracked.lock().unwrap().is_some())
+            .map(|target| target.label.clone())
+            .collect()
+    }
+
+    /// Run until `limit` attempts, then stop as if interrupted
+    fn run_until(attack: &Attack, limit: u64) -> (Option<Position>, Vec<Position>) {
+        let finished = AtomicBool::new(false);
+        let mut checkpoints = Vec::new();
+        let stopped = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                while attack.attempts() < limit && !finished.load(Ordering::SeqCst) {
+                    std::thread::yield_now();
+                }
+                attack.stop();
+            });
+            let stopped = attack.run(words(0), 0, |_, position| checkpoints.push(position));
+            finished.store(true, Ordering::SeqCst);
+            stopped.unwrap()
+        });
+        (stopped, checkpoints)
+    }
+
+    #[test]
+    fn test_kill_and_resume() {
+        for limit in [1, 100, 250, 350] {
+            // Graceful stop: resume exactly at the low-water mark
+            let first = attack();
+            let (stopped, _) = run_until(&first, limit);
+            let position =
+                stopped.unwrap_or_else(|| panic!("limit {} attempts {}", limit, first.attempts()));
+            let second = attack();
+            second
+                .run(words(position.line), position.rule_index, |_, _| {})
+                .unwrap();
+
+            let mut found = cracked(&first);
+            found.extend(cracked(&second));
+            found.sort();
+            assert_eq!(
+                found,
+                vec!["99drow", "word03", "word501"],
+                "limit {}",
+                limit
+            );
+
+            // Only candidates from the low-water mark on are tested again, and
+            // at most one chunk of them had already been tested
+            let remaining = (WORDS - position.line) * RULES - position.rule_index as u64;
+            assert!(second.attempts() <= remaining * 3);
+            assert!(first.attempts() + second.attempts() <= (WORDS + 8) * RULES * 3);
+
+            // Killed: only the periodic checkpoints survive
+            let first = attack();
+            let (_, checkpoints) = run_until(&first, limit);
+            let resume = checkpoints.last().copied().unwrap_or_default();
+            let second = attack();
+            second
+                .run(words(resume.line), resume.rule_index, |_, _| {})
+                .unwrap();
+            let mut found = cracked(&first);
+            found.extend(cracked(&second));
+            found.sort();
+            found.dedup();
+            assert_eq!(
+                found,
+                vec!["99drow", "word03", "word501"],
+                "limit {}",
+                limit
+            );
+        }
+    }
+
+    #[test]
+    fn test_progress_within_chunk() {
+        // A single chunk holds every word, yet progress is reported per batch
+        let attack = attack().with_chunk_size(1000).with_batch_size(16);
+        let mut positions = Vec::new();
+        let stopped = attack
+            .run(words(0), 0, |_, position| positions.push(position))
+            .unwrap();
+        assert_eq!(stopped, None);
+        assert!(positions.len() >= (WORDS * RULES / 16 - 1) as usize);
+        assert!(positions
+            .windows(2)
+            .all(|pair| (pair[0].line, pair[0].rule_index) < (pair[1].line, pair[1].rule_index)));
+
+        // Resuming from one finds the passwords after it
+        let resume = *positions
+            .iter()
+            .find(|position| position.line >= 60)
+            .unwrap();
+        let second = self::attack();
+        second
+            .run(words(resume.line), resume.rule_index, |_, _| {})
+            .unwrap();
+        assert_eq!(cracked(&second), vec!["99drow"]);
+    }
+
+    #[test]
+    fn test_progress_counts_rule_pairs() {
+        // Toggle permutations expand each word to 15 candidates, but the bar
+        // moves by one per (word, rule) pair
+        let mut rules = RuleEngine::new();
+        rules.add_rules(vec![Rule::TogglePermutations(8)]);
+        let pipeline =
+            CandidatePipeline::new(rules, CandidateFilter::new(None, None, Vec::new()), None);
+        let keyspace = WORDS * 2;
+        let attack = Attack::new(pipeline, Targets::new(&[target("missing")]))
+            .with_keyspace(keyspace)
+            .with_chunk_size(8)
+            .with_batch_size(16);
+        let mut positions = Vec::new();
+        attack
+            .run(words(0), 0, |attack, _| {
+                positions.push(attack.stats().progress().unwrap())
+            })
+            .unwrap();
+        assert_eq!(attack.attempts(), WORDS * 16);
+        assert_eq!(attack.stats().progress(), Some(keyspace));
+        assert!(positions.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(positions.iter().all(|&position| position < keyspace));
+    }
+
+    #[test]
+    fn test_progress_counts_skipped_lines() {
+        // Lines the reader skipped between words are part of the keyspace
+        let keyspace = WORDS * RULES;
+        let attack = attack().with_keyspace(keyspace).with_batch_size(16);
+        let mut positions = Vec::new();
+        let gappy = words(0).filter(|entry| entry.as_ref().unwrap().line % 3 != 1);
+        attack
+            .run(gappy, 0, |attack, _| {
+                positions.push(attack.stats().progress().unwrap())
+            })
+            .unwrap();
+        assert_eq!(attack.stats().progress(), Some(keyspace));
+        assert!(positions.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_resume_within_word() {
+        // Only the last rule of the resumed word remains
+        let attack = attack().with_keyspace(1);
+        let stopped = attack.run(words(WORDS - 1), 2, |_, _| {}).unwrap();
+        assert_eq!(stopped, None);
+        assert_eq!(attack.attempts(), 3);
+        assert_eq!(attack.stats().progress(), Some(1));
+        assert_eq!(cracked(&attack), vec!["99drow"]);
+
+        // Stopping before any work keeps the start position
+        let attack = self::attack();
+        attack.stop();
+        let stopped = attack.run(words(40), 1, |_, _| {}).unwrap();
+        assert_eq!(
+            stopped,
+            Some(Position {
+                line: 40,
+                byte_offset: 280,
+                rule_index: 1
+            })
+        );
+        assert_eq!(attack.attempts(), 0);
+    }
+}
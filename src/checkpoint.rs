@@ -1,49 +1,235 @@
 use crate::errors::{CrackerError, Result};
+use crate::rules::Rule;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Bytes hashed at each end of a file for its fingerprint
+const FINGERPRINT_BYTES: u64 = 64 * 1024;
+
+/// Size and content fingerprint of an input file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub path: String,
+    pub size: u64,
+    /// SHA-256 of the size and the first and last 64 KiB
+    pub fingerprint: String,
+}
+
+impl FileFingerprint {
+    /// Fingerprint a file without reading all of it
+    pub fn of(path: &str) -> Result<Self> {
+        let error = |e: std::io::Error| {
+            CrackerError::CheckpointError(format!("Failed to fingerprint {}: {}", path, e))
+        };
+        let mut file = File::open(path).map_err(error)?;
+        let size = file.metadata().map_err(error)?.len();
+
+        let mut hasher = Sha256::new();
+        hasher.update(size.to_le_bytes());
+        let mut buffer = Vec::new();
+        (&mut file)
+            .take(FINGERPRINT_BYTES)
+            .read_to_end(&mut buffer)
+            .map_err(error)?;
+        if size > FINGERPRINT_BYTES {
+            let tail = (size - FINGERPRINT_BYTES).max(FINGERPRINT_BYTES);
+            file.seek(SeekFrom::Start(tail)).map_err(error)?;
+            file.read_to_end(&mut buffer).map_err(error)?;
+        }
+        hasher.update(&buffer);
+
+        Ok(Self {
+            path: path.to_string(),
+            size,
+            fingerprint: hex::encode(hasher.finalize()),
+        })
+    }
+}
+
+/// Everything a checkpoint's position depends on
+///
+/// A position only means "every candidate before this was tested" for the
+/// same targets, inputs, rules and options.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionState {
+    /// Target hashes
+    pub hashes: Vec<String>,
+    /// Wordlists, in session order
+    pub wordlists: Vec<FileFingerprint>,
+    /// SHA-256 of the rule set, one rule per line
+    pub rules_digest: String,
+    /// `wordlist`, `markov` or `prince`
+    pub attack_mode: String,
+    /// Options that change which candidates are generated, by flag name
+    pub options: BTreeMap<String, String>,
+    /// Arguments the session was started with, for `--restore`
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// Directory the session was started in
+    #[serde(default)]
+    pub working_dir: Option<String>,
+}
+
+impl SessionState {
+    /// Digest of the rules in the order they are applied
+    pub fn rules_digest(rules: &[Rule]) -> String {
+        let mut hasher = Sha256::new();
+        for rule in rules {
+            hasher.update(rule.to_string().as_bytes());
+            hasher.update(b"\n");
+        }
+        hex::encode(hasher.finalize())
+    }
+}
 
 /// Checkpoint data for resuming cracking sessions
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Checkpoint {
-    /// Wordlist offset (number of words processed)
+    /// Index of the current file among the session's wordlists
+    #[serde(default)]
+    pub wordlist_file: usize,
+
+    /// Path of the current wordlist file
+    #[serde(default)]
+    pub wordlist_path: Option<String>,
+
+    /// Line of the first word not yet tested with every rule; all earlier
+    /// words are done
     pub wordlist_offset: u64,
 
-    /// Rule index (which rule was being applied)
+    /// Byte offset of `wordlist_offset` in the wordlist, for seeking on resume
+    /// (missing in checkpoints written by older versions)
+    #[serde(default)]
+    pub byte_offset: Option<u64>,
+
+    /// First rule not yet tested on the word at `wordlist_offset`
     pub rule_index: usize,
 
-    /// Total attempts made
+    /// Total attempts made, across every run of the session
     pub total_attempts: u64,
 
-    /// Timestamp of checkpoint
+    /// Seconds spent cracking, across every run of the session (missing in
+    /// checkpoints written by older versions)
+    #[serde(default)]
+    pub elapsed_secs: f64,
+
+    /// Time the checkpoint was written, in RFC 3339
     pub timestamp: String,
+
+    /// Session the position belongs to (missing in checkpoints written by
+    /// older versions)
+    #[serde(default)]
+    pub session: Option<SessionState>,
+
+    /// Percentage of the session's words tested, if the total is known
+    #[serde(default)]
+    pub progress: Option<f64>,
 }
 
 impl Checkpoint {
-    pub fn new(wordlist_offset: u64, rule_index: usize, total_attempts: u64) -> Self {
+    pub fn new(
+        wordlist_file: usize,
+        wordlist_path: &str,
+        wordlist_offset: u64,
+        byte_offset: Option<u64>,
+        rule_index: usize,
+        total_attempts: u64,
+    ) -> Self {
         Self {
+            wordlist_file,
+            wordlist_path: Some(wordlist_path.to_string()),
             wordlist_offset,
+            byte_offset,
             rule_index,
             total_attempts,
-            timestamp: chrono::Utc::now().to_rfc3339(),
+            elapsed_secs: 0.0,
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            session: None,
+            progress: None,
         }
     }
 
+    pub fn with_session(mut self, session: Option<SessionState>) -> Self {
+        self.session = session;
+        self
+    }
+
     /// Save checkpoint to file
+    ///
+    /// The checkpoint is written to `<path>.tmp`, synced to disk and renamed
+    /// over `path`, so a crash never leaves a partial file behind. The
+    /// previous checkpoint is kept as `<path>.bak`.
     pub fn save(&self, path: &str) -> Result<()> {
-        let file = File::create(path).map_err(|e| {
+        let temp = format!("{}.tmp", path);
+        let file = File::create(&temp).map_err(|e| {
             CrackerError::CheckpointError(format!("Failed to create checkpoint file: {}", e))
         })?;
 
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self).map_err(|e| {
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self).map_err(|e| {
             CrackerError::CheckpointError(format!("Failed to write checkpoint: {}", e))
         })?;
+        let file = writer.into_inner().map_err(|e| {
+            CrackerError::CheckpointError(format!("Failed to write checkpoint: {}", e.error()))
+        })?;
+        file.sync_all().map_err(|e| {
+            CrackerError::CheckpointError(format!("Failed to sync checkpoint: {}", e))
+        })?;
+
+        if Path::new(path).exists() {
+            std::fs::rename(path, Self::backup_path(path)).map_err(|e| {
+                CrackerError::CheckpointError(format!("Failed to back up checkpoint: {}", e))
+            })?;
+        }
+        std::fs::rename(&temp, path).map_err(|e| {
+            CrackerError::CheckpointError(format!("Failed to replace checkpoint: {}", e))
+        })?;
+
+        // Make the renames durable too; directories can't be opened on every
+        // platform, so this is best effort
+        let parent = Path::new(path)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
 
         Ok(())
     }
 
+    /// Where the previous checkpoint is kept
+    pub fn backup_path(path: &str) -> String {
+        format!("{}.bak", path)
+    }
+
+    /// Find the checkpoint to resume from
+    ///
+    /// An unreadable checkpoint falls back to the previous one; if that is
+    /// unusable too, the error is returned rather than starting over.
+    pub fn recover(path: &str) -> Result<Recovered> {
+        let backup = Self::backup_path(path);
+        let latest = match Self::load(path) {
+            Ok(checkpoint) => return Ok(Recovered::Latest(checkpoint)),
+            Err(_) if !Path::new(path).exists() && !Path::new(&backup).exists() => {
+                return Ok(Recovered::Missing)
+            }
+            Err(e) => e,
+        };
+        match Self::load(&backup) {
+            Ok(checkpoint) => Ok(Recovered::Backup {
+                checkpoint,
+                error: latest,
+            }),
+            Err(_) => Err(latest),
+        }
+    }
+
     /// Load checkpoint from file
     pub fn load(path: &str) -> Result<Self> {
         if !Path::new(path).exists() {
@@ -58,92 +244,382 @@ impl Checkpoint {
 
         let reader = BufReader::new(file);
         let checkpoint = serde_json::from_reader(reader).map_err(|e| {
-            CrackerError::CheckpointError(format!("Failed to parse checkpoint: {}", e))
+            CrackerError::CheckpointError(format!("Failed to parse checkpoint {}: {}", path, e))
         })?;
 
         Ok(checkpoint)
     }
 
-    /// Delete checkpoint file
+    /// Find the file to resume in among the session's wordlists
+    ///
+    /// The recorded path wins over the recorded position, so adding or
+    /// reordering wordlists between runs still resumes in the right file.
+    pub fn resume_file(&self, wordlists: &[String]) -> Option<usize> {
+        match self.wordlist_path {
+            Some(ref path) => wordlists.iter().position(|w| w == path),
+            None => (self.wordlist_file < wordlists.len()).then_some(self.wordlist_file),
+        }
+    }
+
+    /// Explain why this checkpoint cannot resume the `current` session
+    ///
+    /// Dropping target hashes is allowed (the rest have seen every earlier
+    /// candidate), as are wordlists added after the resume point. An empty
+    /// result means the checkpoint applies.
+    pub fn mismatches(&self, current: &SessionState) -> Vec<String> {
+        let saved = match self.session {
+            Some(ref saved) => saved,
+            None => return Vec::new(),
+        };
+        let mut mismatches = Vec::new();
+
+        let new_hashes: Vec<&String> = current
+            .hashes
+            .iter()
+            .filter(|hash| !saved.hashes.contains(hash))
+            .collect();
+        match new_hashes.as_slice() {
+            [] => {}
+            [hash] => mismatches.push(format!("target hash {} is not in the checkpoint", hash)),
+            hashes => mismatches.push(format!(
+                "{} target hashes are not in the checkpoint",
+                hashes.len()
+            )),
+        }
+
+        if saved.attack_mode != current.attack_mode {
+            mismatches.push(format!(
+                "attack mode was {}, now {}",
+                saved.attack_mode, current.attack_mode
+            ));
+        }
+
+        // Wordlists before the one being resumed have been fully tested
+        let resume_path = self.wordlist_path.as_deref();
+        for wordlist in &current.wordlists {
+            match saved.wordlists.iter().find(|w| w.path == wordlist.path) {
+                Some(old) if old.size != wordlist.size => mismatches.push(format!(
+                    "wordlist {} changed size ({} -> {} bytes)",
+                    wordlist.path, old.size, wordlist.size
+                )),
+                Some(old) if old.fingerprint != wordlist.fingerprint => mismatches.push(format!(
+                    "wordlist {} changed content",
+                    wordlist.path
+                )),
+                Some(_) => {}
+                None => mismatches.push(format!(
+                    "wordlist {} is new and comes before the resume point",
+                    wordlist.path
+                )),
+            }
+            if resume_path == Some(wordlist.path.as_str()) {
+                break;
+            }
+        }
+
+        if saved.rules_digest != current.rules_digest {
+            mismatches.push("rules differ".to_string());
+        }
+
+        let names: BTreeSet<&String> =
+            saved.options.keys().chain(current.options.keys()).collect();
+        for name in names {
+            let old = saved.options.get(name).map_or("(unset)", String::as_str);
+            let new = current.options.get(name).map_or("(unset)", String::as_str);
+            if old != new {
+                mismatches.push(format!("--{} was {}, now {}", name, old, new));
+            }
+        }
+
+        mismatches
+    }
+
+    /// Delete checkpoint file, its backup and any partial write
     pub fn delete(path: &str) -> Result<()> {
-        if Path::new(path).exists() {
-            std::fs::remove_file(path).map_err(|e| {
-                CrackerError::CheckpointError(format!("Failed to delete checkpoint: {}", e))
-            })?;
+        for file in [
+            path.to_string(),
+            Self::backup_path(path),
+            format!("{}.tmp", path),
+        ] {
+            if Path::new(&file).exists() {
+                std::fs::remove_file(&file).map_err(|e| {
+                    CrackerError::CheckpointError(format!("Failed to delete checkpoint: {}", e))
+                })?;
+            }
         }
         Ok(())
     }
 }
 
+/// The checkpoint found by `Checkpoint::recover`
+#[derive(Debug)]
+pub enum Recovered {
+    /// Neither the checkpoint nor its backup exists
+    Missing,
+    Latest(Checkpoint),
+    /// The latest checkpoint was unusable; the previous one was loaded
+    Backup {
+        checkpoint: Checkpoint,
+        error: CrackerError,
+    },
+}
+
 /// Checkpoint manager for periodic saves
 pub struct CheckpointManager {
     path: String,
-    save_interval: u64,
-    last_save: u64,
+    save_interval: Duration,
+    last_save: Instant,
+    started: Instant,
+    /// Attempts and time of earlier runs of a resumed session
+    previous_attempts: u64,
+    previous_elapsed: Duration,
+    session: Option<SessionState>,
+    source_sizes: Vec<u64>,
 }
 
 impl CheckpointManager {
-    pub fn new(path: String, save_interval: u64) -> Self {
+    pub fn new(path: String, save_interval: Duration) -> Self {
+        let now = Instant::now();
         Self {
             path,
             save_interval,
-            last_save: 0,
+            last_save: now,
+            started: now,
+            previous_attempts: 0,
+            previous_elapsed: Duration::ZERO,
+            session: None,
+            source_sizes: Vec::new(),
         }
     }
 
-    /// Check if checkpoint should be saved based on attempts
-    pub fn should_save(&self, total_attempts: u64) -> bool {
-        total_attempts - self.last_save >= self.save_interval
+    /// Continue the attempt count and runtime of a resumed checkpoint
+    pub fn with_resumed(mut self, checkpoint: Option<&Checkpoint>) -> Self {
+        if let Some(checkpoint) = checkpoint {
+            self.previous_attempts = checkpoint.total_attempts;
+            self.previous_elapsed = Duration::from_secs_f64(checkpoint.elapsed_secs.max(0.0));
+        }
+        self
     }
 
-    /// Save checkpoint if interval has passed
+    /// Record the session in every checkpoint written
+    pub fn with_session(mut self, session: SessionState) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Number of words in each source, for recording progress
+    pub fn with_source_sizes(mut self, sizes: Vec<u64>) -> Self {
+        self.source_sizes = sizes;
+        self
+    }
+
+    /// Check if the save interval has passed since the last save
+    pub fn should_save(&self) -> bool {
+        self.last_save.elapsed() >= self.save_interval
+    }
+
+    /// Attempts of the whole session, given this run's attempts
+    pub fn total_attempts(&self, attempts: u64) -> u64 {
+        self.previous_attempts + attempts
+    }
+
+    /// Runtime of the whole session, including earlier runs
+    pub fn total_elapsed(&self) -> Duration {
+        self.previous_elapsed + self.started.elapsed()
+    }
+
+    /// Save checkpoint if interval has passed; returns whether it saved
     pub fn maybe_save(
         &mut self,
+        wordlist_file: usize,
+        wordlist_path: &str,
         wordlist_offset: u64,
+        byte_offset: Option<u64>,
         rule_index: usize,
         total_attempts: u64,
-    ) -> Result<()> {
-        if self.should_save(total_attempts) {
-            let checkpoint = Checkpoint::new(wordlist_offset, rule_index, total_attempts);
-            checkpoint.save(&self.path)?;
-            self.last_save = total_attempts;
+    ) -> Result<bool> {
+        if !self.should_save() {
+            return Ok(false);
         }
-        Ok(())
+        // A failed save is retried after another interval, not on every call
+        self.last_save = Instant::now();
+        self.save(
+            wordlist_file,
+            wordlist_path,
+            wordlist_offset,
+            byte_offset,
+            rule_index,
+            total_attempts,
+        )?;
+        Ok(true)
     }
 
-    /// Force save checkpoint
+    /// Force save checkpoint; `total_attempts` counts this run only
     pub fn save(
         &mut self,
+        wordlist_file: usize,
+        wordlist_path: &str,
         wordlist_offset: u64,
+        byte_offset: Option<u64>,
         rule_index: usize,
         total_attempts: u64,
     ) -> Result<()> {
-        let checkpoint = Checkpoint::new(wordlist_offset, rule_index, total_attempts);
+        let mut checkpoint = Checkpoint::new(
+            wordlist_file,
+            wordlist_path,
+            wordlist_offset,
+            byte_offset,
+            rule_index,
+            self.total_attempts(total_attempts),
+        )
+        .with_session(self.session.clone());
+        checkpoint.elapsed_secs = self.total_elapsed().as_secs_f64();
+        checkpoint.progress = self.progress(wordlist_file, wordlist_offset);
         checkpoint.save(&self.path)?;
-        self.last_save = total_attempts;
+        self.last_save = Instant::now();
         Ok(())
     }
+
+    /// Percentage of all sources' words before `wordlist_offset` of `wordlist_file`
+    fn progress(&self, wordlist_file: usize, wordlist_offset: u64) -> Option<f64> {
+        let total: u64 = self.source_sizes.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let done: u64 = self.source_sizes.iter().take(wordlist_file).sum::<u64>() + wordlist_offset;
+        Some((done as f64 * 100.0 / total as f64).min(100.0))
+    }
 }
 
-// Simple implementation without chrono dependency
-mod chrono {
-    pub struct Utc;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    impl Utc {
-        pub fn now() -> DateTime {
-            DateTime
+    fn session(hashes: &[&str], wordlists: Vec<FileFingerprint>) -> SessionState {
+        SessionState {
+            hashes: hashes.iter().map(|h| h.to_string()).collect(),
+            wordlists,
+            rules_digest: SessionState::rules_digest(&[Rule::None]),
+            attack_mode: "wordlist".to_string(),
+            options: BTreeMap::new(),
+            command: Vec::new(),
+            working_dir: None,
         }
     }
 
-    pub struct DateTime;
+    #[test]
+    fn test_session_mismatches() {
+        let path = std::env::temp_dir()
+            .join(format!("pbkdf2_cracker_{}_checkpoint.txt", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        std::fs::write(&path, "alpha\nbeta\n").unwrap();
+        let first = FileFingerprint::of(&path).unwrap();
+        let extra = FileFingerprint {
+            path: "extra.txt".to_string(),
+            size: 1,
+            fingerprint: "00".to_string(),
+        };
 
-    impl DateTime {
-        pub fn to_rfc3339(&self) -> String {
-            // Simple timestamp format
-            format!("{}", std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs())
-        }
+        let checkpoint = Checkpoint::new(0, &path, 1, Some(6), 0, 1)
+            .with_session(Some(session(&["a", "b"], vec![first.clone()])));
+
+        // Dropping a hash and adding a later wordlist keep the position valid
+        let current = session(&["b"], vec![first.clone(), extra.clone()]);
+        assert!(checkpoint.mismatches(&current).is_empty());
+
+        let mut current = session(&["a", "c"], vec![extra, first]);
+        current.rules_digest = SessionState::rules_digest(&[Rule::Reverse]);
+        current.options.insert("min-len".to_string(), "8".to_string());
+        assert_eq!(
+            checkpoint.mismatches(&current),
+            vec![
+                "target hash c is not in the checkpoint".to_string(),
+                "wordlist extra.txt is new and comes before the resume point".to_string(),
+                "rules differ".to_string(),
+                "--min-len was (unset), now 8".to_string(),
+            ]
+        );
+
+        std::fs::write(&path, "alpha\nbetb\n").unwrap();
+        let changed = session(&["a"], vec![FileFingerprint::of(&path).unwrap()]);
+        assert_eq!(
+            checkpoint.mismatches(&changed),
+            vec![format!("wordlist {} changed content", path)]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_save_and_recovery() {
+        let path = std::env::temp_dir()
+            .join(format!("pbkdf2_cracker_{}_recover.json", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let backup = Checkpoint::backup_path(&path);
+        let offset = |recovered: Recovered| match recovered {
+            Recovered::Latest(checkpoint) => ("latest", checkpoint.wordlist_offset),
+            Recovered::Backup { checkpoint, .. } => ("backup", checkpoint.wordlist_offset),
+            Recovered::Missing => ("missing", 0),
+        };
+        assert_eq!(offset(Checkpoint::recover(&path).unwrap()), ("missing", 0));
+
+        Checkpoint::new(0, "words.txt", 10, None, 0, 10).save(&path).unwrap();
+        Checkpoint::new(0, "words.txt", 20, None, 0, 20).save(&path).unwrap();
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+        assert_eq!(offset(Checkpoint::recover(&path).unwrap()), ("latest", 20));
+
+        // A torn write falls back to the previous checkpoint
+        std::fs::write(&path, "{\"wordlist_offset\": 3").unwrap();
+        assert_eq!(offset(Checkpoint::recover(&path).unwrap()), ("backup", 10));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(offset(Checkpoint::recover(&path).unwrap()), ("backup", 10));
+
+        // With no usable checkpoint left, resuming is an error
+        std::fs::write(&path, "").unwrap();
+        std::fs::write(&backup, "").unwrap();
+        assert!(Checkpoint::recover(&path).is_err());
+
+        Checkpoint::delete(&path).unwrap();
+        assert!(!Path::new(&path).exists() && !Path::new(&backup).exists());
+    }
+
+    #[test]
+    fn test_manager_accumulates_resumed_runs() {
+        let path = std::env::temp_dir()
+            .join(format!("pbkdf2_cracker_{}_manager.json", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let mut previous = Checkpoint::new(0, "words.txt", 10, None, 0, 500);
+        previous.elapsed_secs = 3600.0;
+
+        let mut manager = CheckpointManager::new(path.clone(), Duration::from_secs(3600))
+            .with_resumed(Some(&previous))
+            .with_source_sizes(vec![40, 60]);
+        assert!(!manager.should_save());
+        assert!(!manager.maybe_save(0, "words.txt", 20, None, 0, 100).unwrap());
+        assert!(!Path::new(&path).exists());
+
+        manager.save(1, "more.txt", 30, None, 0, 100).unwrap();
+        let saved = Checkpoint::load(&path).unwrap();
+        assert_eq!(saved.total_attempts, 600);
+        assert!(saved.elapsed_secs >= 3600.0);
+        assert_eq!(saved.progress, Some(70.0));
+        assert!(chrono::DateTime::parse_from_rfc3339(&saved.timestamp).is_ok());
+
+        let mut manager = CheckpointManager::new(path.clone(), Duration::ZERO);
+        assert!(manager.should_save());
+        assert!(manager.maybe_save(0, "words.txt", 5, None, 0, 5).unwrap());
+        assert_eq!(Checkpoint::load(&path).unwrap().total_attempts, 5);
+
+        // A failed save is reported once per interval
+        let missing = std::env::temp_dir().join("pbkdf2_cracker_missing_dir/manager.json");
+        let mut manager =
+            CheckpointManager::new(missing.to_string_lossy().into_owned(), Duration::from_millis(20));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(manager.maybe_save(0, "words.txt", 5, None, 0, 5).is_err());
+        assert!(!manager.maybe_save(0, "words.txt", 5, None, 0, 5).unwrap());
+
+        Checkpoint::delete(&path).unwrap();
     }
 }
@@ -1,4 +1,10 @@
-use clap::Parser;
+use crate::filter::{CandidateFilter, CharClass};
+use crate::prince::PrinceOptions;
+use crate::report::OutputMode;
+use crate::wordlist::LineOptions;
+use crate::wordprep::PrepOptions;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -7,16 +13,26 @@ use clap::Parser;
     about = "Production-grade PBKDF2-SHA256 password cracker for Flask/Werkzeug hashes",
     long_about = "A high-performance Rust CLI tool for cracking Flask/Werkzeug PBKDF2-SHA256 hashes in CTF/HTB environments.\n\n\
                   Hash Format: pbkdf2:sha256:<iterations>$<salt>$<hex_digest>\n\
-                  Example: pbkdf2:sha256:600000$AMtzteQIG7yAbZIa$0673ad90a0b4afb19d662336f0fce3a9edd0b7b19193717be28ce4d66c887133"
+                  Example: pbkdf2:sha256:600000$AMtzteQIG7yAbZIa$0673ad90a0b4afb19d662336f0fce3a9edd0b7b19193717be28ce4d66c887133",
+    subcommand_negates_reqs = true
 )]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Target hash to crack
-    #[arg(long, required_unless_present = "verify")]
+    #[arg(long, required_unless_present_any = ["verify", "hash_file", "restore"], conflicts_with = "hash_file")]
     pub hash: Option<String>,
 
-    /// Path to wordlist file
-    #[arg(long, required_unless_present = "verify")]
-    pub wordlist: Option<String>,
+    /// File of hashes to crack, one per line, each optionally preceded by
+    /// `:`-separated account fields (username, email, company, ...)
+    #[arg(long, value_name = "FILE")]
+    pub hash_file: Option<String>,
+
+    /// Wordlist files or directories, read in order as one keyspace
+    /// (repeat the flag or pass several paths)
+    #[arg(long, num_args = 1.., required_unless_present_any = ["verify", "association", "markov_mask", "restore"])]
+    pub wordlist: Vec<String>,
 
     /// Path to rules file (optional)
     #[arg(long)]
@@ -34,21 +50,330 @@ pub struct Cli {
     #[arg(long, default_value = "checkpoint.json")]
     pub checkpoint: String,
 
+    /// Seconds between periodic checkpoints
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub checkpoint_interval: u64,
+
+    /// Keep the checkpoint as a named session in the state directory
+    /// (instead of --checkpoint)
+    #[arg(long, value_name = "NAME", conflicts_with = "checkpoint")]
+    pub session: Option<String>,
+
+    /// Resume a named session with the options it was started with
+    /// (only the output options may be given alongside)
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = [
+            "hash", "hash_file", "wordlist", "rules", "threads", "resume", "checkpoint",
+            "checkpoint_interval", "session", "no_index", "trim_whitespace", "keep_empty",
+            "verify", "default_rules", "leet_rules", "min_len", "max_len", "require",
+            "generate_rules", "rule_seed", "save_hit_rules", "dedup_memory", "association",
+            "domain", "markov_mask", "markov_stats", "markov_threshold", "prince",
+            "prince_min_elements", "prince_max_elements", "prince_min_len", "prince_max_len",
+        ]
+    )]
+    pub restore: Option<String>,
+
+    /// Print newline-delimited JSON status events instead of human output
+    #[arg(long, conflicts_with_all = ["verify", "quiet"])]
+    pub status_json: bool,
+
+    /// Seconds between JSON progress events
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 5,
+        requires = "status_json"
+    )]
+    pub status_interval: u64,
+
+    /// Don't read or write the wordlist index cache (<wordlist>.idx)
+    #[arg(long)]
+    pub no_index: bool,
+
+    /// Trim leading and trailing whitespace from wordlist lines
+    /// (by default only the line terminator is removed)
+    #[arg(long)]
+    pub trim_whitespace: bool,
+
+    /// Test empty wordlist lines as empty-string candidates instead of skipping them
+    #[arg(long)]
+    pub keep_empty: bool,
+
     /// Password to verify (verification mode)
     #[arg(long)]
     pub verify: Option<String>,
 
     /// Enable verbose output
-    #[arg(long, short)]
+    #[arg(long, short, conflicts_with = "quiet")]
     pub verbose: bool,
 
+    /// Print only cracked hashes, as `hash:password` lines
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+
+    /// Plain ASCII output without emoji, colors or box drawing
+    #[arg(long, global = true, visible_alias = "no-color")]
+    pub plain: bool,
+
     /// Use default rule mutations
     #[arg(long)]
     pub default_rules: bool,
+
+    /// Use leetspeak, substitution and toggle-case rule mutations
+    #[arg(long, conflicts_with_all = ["default_rules", "rules"])]
+    pub leet_rules: bool,
+
+    /// Skip candidates shorter than this many characters
+    #[arg(long)]
+    pub min_len: Option<usize>,
+
+    /// Skip candidates longer than this many characters
+    #[arg(long)]
+    pub max_len: Option<usize>,
+
+    /// Skip candidates missing a character class (lower, upper, digit, special)
+    #[arg(long, value_delimiter = ',', value_parser = parse_char_class)]
+    pub require: Vec<CharClass>,
+
+    /// Add this many randomly generated rule chains
+    #[arg(long, value_name = "N")]
+    pub generate_rules: Option<usize>,
+
+    /// Seed for --generate-rules (random if not set)
+    #[arg(long, requires = "generate_rules")]
+    pub rule_seed: Option<u64>,
+
+    /// Write the rules that cracked passwords to this rules file
+    #[arg(long, value_name = "FILE")]
+    pub save_hit_rules: Option<String>,
+
+    /// Skip candidates already tested this run, using a Bloom filter of this many MB
+    #[arg(long, value_name = "MB")]
+    pub dedup_memory: Option<usize>,
+
+    /// Test each hash against words derived from its own account fields
+    /// before the wordlist
+    #[arg(long)]
+    pub association: bool,
+
+    /// Application domain to derive association words from
+    #[arg(long, requires = "association")]
+    pub domain: Option<String>,
+
+    /// Generate candidates from a mask in Markov probability order instead of
+    /// reading a wordlist (?l ?u ?d ?s ?a ?b, other characters are literal)
+    #[arg(
+        long,
+        value_name = "MASK",
+        conflicts_with = "wordlist",
+        requires = "markov_stats"
+    )]
+    pub markov_mask: Option<String>,
+
+    /// Markov statistics file written by the train subcommand
+    #[arg(long, value_name = "FILE", requires = "markov_mask")]
+    pub markov_stats: Option<String>,
+
+    /// Keep only the N most likely characters per position (0 = all)
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "markov_mask")]
+    pub markov_threshold: usize,
+
+    /// Chain words of the wordlist into passphrases (PRINCE) instead of
+    /// testing them one by one
+    #[arg(long, requires = "wordlist")]
+    pub prince: bool,
+
+    /// Fewest words chained into one PRINCE candidate
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "prince")]
+    pub prince_min_elements: usize,
+
+    /// Most words chained into one PRINCE candidate
+    #[arg(long, value_name = "N", default_value_t = 8, requires = "prince")]
+    pub prince_max_elements: usize,
+
+    /// Shortest PRINCE candidate, in bytes
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "prince")]
+    pub prince_min_len: usize,
+
+    /// Longest PRINCE candidate, in bytes
+    #[arg(long, value_name = "N", default_value_t = 16, requires = "prince")]
+    pub prince_max_len: usize,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Prepare wordlists with the same line handling the cracker uses
+    #[command(subcommand)]
+    Wordlist(WordlistCommand),
+
+    /// Build a Markov statistics file from wordlists
+    Train(TrainArgs),
+
+    /// Manage named sessions
+    #[command(subcommand)]
+    Sessions(SessionsCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionsCommand {
+    /// List saved sessions with their target and progress
+    List,
+
+    /// Delete a saved session
+    Delete {
+        /// Session name
+        name: String,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct TrainArgs {
+    /// Training wordlist files or directories
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+
+    /// Statistics file to write
+    #[arg(long, short)]
+    pub output: String,
+
+    /// Trim leading and trailing whitespace from lines
+    #[arg(long)]
+    pub trim_whitespace: bool,
+
+    /// Keep empty lines as empty words
+    #[arg(long)]
+    pub keep_empty: bool,
+}
+
+impl TrainArgs {
+    /// How training lines are turned into words
+    pub fn line_options(&self) -> LineOptions {
+        LineOptions {
+            trim_whitespace: self.trim_whitespace,
+            keep_empty: self.keep_empty,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WordlistCommand {
+    /// Concatenate wordlists in order
+    Merge(WordlistArgs),
+
+    /// Remove duplicates, keeping the first occurrence of each word
+    Dedup(WordlistArgs),
+
+    /// Remove duplicates and order words by frequency, most common first
+    Sort(WordlistArgs),
+
+    /// Deal words round-robin into N shards (<output>.1 .. <output>.N)
+    Split {
+        #[command(flatten)]
+        args: WordlistArgs,
+
+        /// Number of shards
+        #[arg(long, value_name = "N")]
+        shards: usize,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct WordlistArgs {
+    /// Input wordlist files or directories, read in order
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+
+    /// Output file (prefix of the shard files for split)
+    #[arg(long, short)]
+    pub output: String,
+
+    /// Drop words shorter than this many characters
+    #[arg(long)]
+    pub min_len: Option<usize>,
+
+    /// Drop words longer than this many characters
+    #[arg(long)]
+    pub max_len: Option<usize>,
+
+    /// Drop words missing a character class (lower, upper, digit, special)
+    #[arg(long, value_delimiter = ',', value_parser = parse_char_class)]
+    pub require: Vec<CharClass>,
+
+    /// Trim leading and trailing whitespace from lines
+    #[arg(long)]
+    pub trim_whitespace: bool,
+
+    /// Keep empty lines as empty words
+    #[arg(long)]
+    pub keep_empty: bool,
+
+    /// Memory for sorting before spilling to disk
+    #[arg(long, value_name = "MB", default_value_t = 256)]
+    pub memory: usize,
+
+    /// Directory for temporary sort files (default: system temp directory)
+    #[arg(long, value_name = "DIR")]
+    pub temp_dir: Option<PathBuf>,
+}
+
+impl WordlistCommand {
+    pub fn validate(&self) -> Result<(), String> {
+        let args = match self {
+            WordlistCommand::Split { args, shards } => {
+                if *shards == 0 {
+                    return Err("--shards must be greater than 0".to_string());
+                }
+                args
+            }
+            WordlistCommand::Merge(args)
+            | WordlistCommand::Dedup(args)
+            | WordlistCommand::Sort(args) => args,
+        };
+
+        if args.memory == 0 {
+            return Err("--memory must be greater than 0".to_string());
+        }
+
+        if let (Some(min), Some(max)) = (args.min_len, args.max_len) {
+            if min > max {
+                return Err("--min-len must not be greater than --max-len".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl WordlistArgs {
+    /// Build the preprocessing settings from the command line
+    pub fn prep_options(&self) -> PrepOptions {
+        PrepOptions {
+            line_options: LineOptions {
+                trim_whitespace: self.trim_whitespace,
+                keep_empty: self.keep_empty,
+            },
+            filter: CandidateFilter::new(self.min_len, self.max_len, self.require.clone()),
+            memory_bytes: self.memory * 1024 * 1024,
+            temp_dir: self.temp_dir.clone().unwrap_or_else(std::env::temp_dir),
+        }
+    }
 }
 
 impl Cli {
     pub fn validate(&self) -> Result<(), String> {
+        match self.command {
+            Some(Command::Wordlist(ref command)) => return command.validate(),
+            Some(Command::Train(_)) | Some(Command::Sessions(_)) => return Ok(()),
+            None => {}
+        }
+
+        // The restored session's own options are validated when it is loaded
+        if self.restore.is_some() {
+            return Ok(());
+        }
+
         // Verify mode validation
         if self.verify.is_some() {
             if self.hash.is_none() {
@@ -58,11 +383,11 @@ impl Cli {
         }
 
         // Crack mode validation
-        if self.hash.is_none() {
-            return Err("--hash is required".to_string());
+        if self.hash.is_none() && self.hash_file.is_none() {
+            return Err("--hash or --hash-file is required".to_string());
         }
 
-        if self.wordlist.is_none() {
+        if self.wordlist.is_empty() && !self.association && self.markov_mask.is_none() {
             return Err("--wordlist is required".to_string());
         }
 
@@ -70,10 +395,88 @@ impl Cli {
             return Err("--threads must be greater than 0".to_string());
         }
 
+        if self.status_interval == 0 {
+            return Err("--status-interval must be greater than 0".to_string());
+        }
+
+        if self.checkpoint_interval == 0 {
+            return Err("--checkpoint-interval must be greater than 0".to_string());
+        }
+
+        if self.dedup_memory == Some(0) {
+            return Err("--dedup-memory must be greater than 0".to_string());
+        }
+
+        if let (Some(min), Some(max)) = (self.min_len, self.max_len) {
+            if min > max {
+                return Err("--min-len must not be greater than --max-len".to_string());
+            }
+        }
+
+        if self.prince {
+            if self.prince_min_elements == 0 {
+                return Err("--prince-min-elements must be greater than 0".to_string());
+            }
+            if self.prince_min_elements > self.prince_max_elements {
+                return Err(
+                    "--prince-min-elements must not be greater than --prince-max-elements"
+                        .to_string(),
+                );
+            }
+            if self.prince_min_len > self.prince_max_len {
+                return Err(
+                    "--prince-min-len must not be greater than --prince-max-len".to_string()
+                );
+            }
+        }
+
         Ok(())
     }
 
+    /// Output mode chosen by --quiet and --status-json
+    pub fn output_mode(&self) -> OutputMode {
+        if self.status_json {
+            OutputMode::Json
+        } else if self.quiet {
+            OutputMode::Quiet
+        } else {
+            OutputMode::Human
+        }
+    }
+
     pub fn is_verify_mode(&self) -> bool {
         self.verify.is_some()
     }
+
+    /// Build the password policy filter from the command line
+    pub fn candidate_filter(&self) -> CandidateFilter {
+        CandidateFilter::new(self.min_len, self.max_len, self.require.clone())
+    }
+
+    /// Chain limits for PRINCE mode
+    pub fn prince_options(&self) -> PrinceOptions {
+        PrinceOptions {
+            min_elements: self.prince_min_elements,
+            max_elements: self.prince_max_elements,
+            min_len: self.prince_min_len,
+            max_len: self.prince_max_len,
+        }
+    }
+
+    /// How wordlist lines are turned into base words
+    pub fn line_options(&self) -> LineOptions {
+        LineOptions {
+            trim_whitespace: self.trim_whitespace,
+            keep_empty: self.keep_empty,
+        }
+    }
+}
+
+fn parse_char_class(name: &str) -> Result<CharClass, String> {
+    CharClass::parse(name).ok_or_else(|| {
+        format!(
+            "unknown character class '{}' (expected lower, upper, digit or special)",
+            name
+        )
+    })
 }
@@ -14,18 +14,18 @@ impl Pbkdf2Cracker {
 
     /// Test a password candidate against the target hash
     /// Uses constant-time comparison to prevent timing attacks
-    pub fn test_password(&self, password: &str) -> bool {
+    pub fn test_password(&self, password: &[u8]) -> bool {
         let derived = self.derive_key(password);
         constant_time_compare(&derived, &self.parsed_hash.digest)
     }
 
     /// Derive PBKDF2-HMAC-SHA256 key from password
     /// This implements the same algorithm used by Flask/Werkzeug
-    fn derive_key(&self, password: &str) -> Vec<u8> {
+    fn derive_key(&self, password: &[u8]) -> Vec<u8> {
         let mut output = vec![0u8; 32]; // SHA256 produces 32 bytes
 
         pbkdf2_hmac::<Sha256>(
-            password.as_bytes(),
+            password,
             &self.parsed_hash.salt,
             self.parsed_hash.iterations,
             &mut output,
@@ -0,0 +1,105 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of bit positions set per item in the Bloom filter
+const BLOOM_HASHES: u64 = 7;
+
+/// Remove duplicate candidates, keeping the first occurrence of each
+/// Returns the number of candidates removed
+pub fn dedup_in_place<T, F>(items: &mut Vec<T>, candidate: F) -> usize
+where
+    F: Fn(&T) -> &[u8],
+{
+    let before = items.len();
+    let mut seen = HashSet::with_capacity(before);
+    items.retain(|item| seen.insert(candidate(item).to_vec()));
+    before - items.len()
+}
+
+/// Fixed-size Bloom filter for skipping candidates already tested in this run
+///
+/// Memory use is bounded by the size given at construction. Once the filter
+/// fills up, false positives grow and a small fraction of never-tested
+/// candidates may be skipped, so size it generously for long runs.
+pub struct BloomFilter {
+    bits: Vec<AtomicU64>,
+    num_bits: u64,
+}
+
+impl BloomFilter {
+    /// Create a filter using roughly `megabytes` of memory
+    pub fn with_memory_mb(megabytes: usize) -> Self {
+        let words = (megabytes.max(1) * 1024 * 1024 / 8).max(1);
+        Self {
+            bits: (0..words).map(|_| AtomicU64::new(0)).collect(),
+            num_bits: words as u64 * 64,
+        }
+    }
+
+    /// Record an item, returning true if it was (probably) seen before
+    pub fn check_and_insert(&self, item: &[u8]) -> bool {
+        let (h1, h2) = Self::hash_pair(item);
+        let mut seen = true;
+
+        // Kirsch-Mitzenmacher double hashing: bit_i = h1 + i * h2
+        for i in 0..BLOOM_HASHES {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits;
+            let mask = 1u64 << (bit % 64);
+            let previous = self.bits[(bit / 64) as usize].fetch_or(mask, Ordering::Relaxed);
+            if previous & mask == 0 {
+                seen = false;
+            }
+        }
+
+        seen
+    }
+
+    fn hash_pair(item: &[u8]) -> (u64, u64) {
+        let mut first = DefaultHasher::new();
+        item.hash(&mut first);
+        let h1 = first.finish();
+
+        let mut second = DefaultHasher::new();
+        h1.hash(&mut second);
+        item.hash(&mut second);
+        // An odd step guarantees distinct positions for every hash index
+        (h1, second.finish() | 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_in_place_keeps_first_seen_order() {
+        let mut candidates = vec![
+            "abc".to_string(),
+            "cba".to_string(),
+            "abc".to_string(),
+            "aba".to_string(),
+            "aba".to_string(),
+        ];
+        assert_eq!(dedup_in_place(&mut candidates, |c| c.as_bytes()), 2);
+        assert_eq!(candidates, vec!["abc", "cba", "aba"]);
+
+        let mut tagged = vec![
+            (0, "abc".to_string()),
+            (1, "abc".to_string()),
+            (2, "cba".to_string()),
+        ];
+        assert_eq!(dedup_in_place(&mut tagged, |(_, c)| c.as_bytes()), 1);
+        assert_eq!(tagged, vec![(0, "abc".to_string()), (2, "cba".to_string())]);
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let bloom = BloomFilter::with_memory_mb(1);
+        assert!(!bloom.check_and_insert(b"password1"));
+        assert!(!bloom.check_and_insert(b"password2"));
+        assert!(bloom.check_and_insert(b"password1"));
+        assert!(bloom.check_and_insert(b"password2"));
+    }
+}
@@ -0,0 +1,108 @@
+use std::borrow::Cow;
+
+/// Decode a `$HEX[...]` wordlist entry into raw bytes
+/// Returns `None` if the entry is not in that form or the hex is invalid.
+pub fn decode_hex_entry(entry: &[u8]) -> Option<Vec<u8>> {
+    let inner = entry.strip_prefix(b"$HEX[")?.strip_suffix(b"]")?;
+    hex::decode(inner).ok()
+}
+
+/// Format a candidate for display
+///
+/// Printable UTF-8 is shown as-is; anything else (invalid UTF-8, control
+/// characters, or text that would itself look like `$HEX[...]`) is shown in
+/// `$HEX[...]` form so it can be copied back into a wordlist exactly.
+pub fn format_candidate(candidate: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(candidate) {
+        Ok(text) if !text.chars().any(char::is_control) && !text.starts_with("$HEX[") => {
+            Cow::Borrowed(text)
+        }
+        _ => Cow::Owned(format!("$HEX[{}]", hex::encode(candidate))),
+    }
+}
+
+/// Encode a word as a wordlist line (without the terminator)
+///
+/// Words that would not read back unchanged (containing a newline, ending
+/// in a carriage return, or looking like `$HEX[...]`) are written in
+/// `$HEX[...]` form; everything else is written as raw bytes.
+pub fn encode_line(word: &[u8]) -> Cow<'_, [u8]> {
+    if word.contains(&b'\n') || word.ends_with(b"\r") || decode_hex_entry(word).is_some() {
+        Cow::Owned(format!("$HEX[{}]", hex::encode(word)).into_bytes())
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+/// View a candidate as text for character-level processing
+///
+/// Valid UTF-8 is borrowed unchanged. Other bytes are mapped one-to-one to
+/// the characters U+0000..U+00FF (Latin-1), so rules still see one
+/// character per byte; `to_bytes` reverses the mapping.
+pub fn to_text(candidate: &[u8]) -> (Cow<'_, str>, bool) {
+    match std::str::from_utf8(candidate) {
+        Ok(text) => (Cow::Borrowed(text), true),
+        Err(_) => (
+            Cow::Owned(candidate.iter().map(|&b| b as char).collect()),
+            false,
+        ),
+    }
+}
+
+/// Convert text produced by `to_text` back to bytes
+///
+/// Characters added by rules that do not fit in a byte are written as UTF-8.
+pub fn to_bytes(text: String, utf8: bool) -> Vec<u8> {
+    if utf8 {
+        return text.into_bytes();
+    }
+
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        if (c as u32) <= 0xff {
+            bytes.push(c as u8);
+        } else {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_entries_and_display() {
+        assert_eq!(
+            decode_hex_entry(b"$HEX[70e4737300]"),
+            Some(vec![0x70, 0xe4, 0x73, 0x73, 0x00])
+        );
+        assert_eq!(decode_hex_entry(b"$HEX[zz]"), None);
+        assert_eq!(decode_hex_entry(b"password"), None);
+
+        assert_eq!(format_candidate(b"password"), "password");
+        assert_eq!(format_candidate("пароль".as_bytes()), "пароль");
+        assert_eq!(format_candidate(b"p\xe4ss"), "$HEX[70e47373]");
+        assert_eq!(format_candidate(b"tab\there"), "$HEX[7461620968657265]");
+        assert_eq!(format_candidate(b"$HEX[41]"), "$HEX[244845585b34315d]");
+    }
+
+    #[test]
+    fn test_encode_line() {
+        assert_eq!(encode_line(b" spaced\t").as_ref(), b" spaced\t");
+        assert_eq!(encode_line(b"p\xe4ss").as_ref(), b"p\xe4ss");
+        assert_eq!(encode_line(b"two\nlines").as_ref(), b"$HEX[74776f0a6c696e6573]");
+        assert_eq!(encode_line(b"cr\r").as_ref(), b"$HEX[63720d]");
+        assert_eq!(encode_line(b"$HEX[41]").as_ref(), b"$HEX[244845585b34315d]");
+    }
+
+    #[test]
+    fn test_latin1_round_trip() {
+        let (text, utf8) = to_text(b"p\xe4ss");
+        assert!(!utf8);
+        assert_eq!(text, "päss");
+        assert_eq!(to_bytes(text.to_uppercase(), utf8), b"P\xc4SS");
+    }
+}
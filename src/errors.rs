@@ -8,9 +8,13 @@ pub enum CrackerError {
     InvalidDigest(String),
     WordlistNotFound(String),
     WordlistReadError(String),
+    WordlistWriteError(String),
     RulesFileError(String),
     CheckpointError(String),
     VerificationError(String),
+    MarkovError(String),
+    PrinceError(String),
+    SessionError(String),
     Pbkdf2Error(String),
 }
 
@@ -23,9 +27,13 @@ impl fmt::Display for CrackerError {
             CrackerError::InvalidDigest(msg) => write!(f, "Invalid digest: {}", msg),
             CrackerError::WordlistNotFound(msg) => write!(f, "Wordlist not found: {}", msg),
             CrackerError::WordlistReadError(msg) => write!(f, "Wordlist read error: {}", msg),
+            CrackerError::WordlistWriteError(msg) => write!(f, "Wordlist write error: {}", msg),
             CrackerError::RulesFileError(msg) => write!(f, "Rules file error: {}", msg),
             CrackerError::CheckpointError(msg) => write!(f, "Checkpoint error: {}", msg),
             CrackerError::VerificationError(msg) => write!(f, "Verification error: {}", msg),
+            CrackerError::MarkovError(msg) => write!(f, "Markov error: {}", msg),
+            CrackerError::PrinceError(msg) => write!(f, "PRINCE error: {}", msg),
+            CrackerError::SessionError(msg) => write!(f, "Session error: {}", msg),
             CrackerError::Pbkdf2Error(msg) => write!(f, "PBKDF2 error: {}", msg),
         }
     }
@@ -0,0 +1,108 @@
+use crate::encoding::to_text;
+use std::fmt;
+
+/// Character classes used by password policy filters and rejection rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Lower,   // a-z and other lowercase letters
+    Upper,   // A-Z and other uppercase letters
+    Digit,   // 0-9
+    Special, // Anything that is not a letter or digit
+}
+
+impl CharClass {
+    /// Check whether a character belongs to this class
+    pub fn matches(&self, c: char) -> bool {
+        match self {
+            CharClass::Lower => c.is_lowercase(),
+            CharClass::Upper => c.is_uppercase(),
+            CharClass::Digit => c.is_ascii_digit(),
+            CharClass::Special => !c.is_alphanumeric(),
+        }
+    }
+
+    /// Parse a class name: "lower", "upper", "digit" or "special"
+    pub fn parse(name: &str) -> Option<CharClass> {
+        match name.trim() {
+            "lower" => Some(CharClass::Lower),
+            "upper" => Some(CharClass::Upper),
+            "digit" => Some(CharClass::Digit),
+            "special" => Some(CharClass::Special),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CharClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CharClass::Lower => "lower",
+            CharClass::Upper => "upper",
+            CharClass::Digit => "digit",
+            CharClass::Special => "special",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Global password policy applied to candidates after rule expansion
+#[derive(Debug, Clone, Default)]
+pub struct CandidateFilter {
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+    require: Vec<CharClass>,
+}
+
+impl CandidateFilter {
+    pub fn new(min_len: Option<usize>, max_len: Option<usize>, require: Vec<CharClass>) -> Self {
+        Self {
+            min_len,
+            max_len,
+            require,
+        }
+    }
+
+    /// Check if the filter rejects anything at all
+    pub fn is_active(&self) -> bool {
+        self.min_len.is_some() || self.max_len.is_some() || !self.require.is_empty()
+    }
+
+    /// Check whether a candidate satisfies the policy
+    /// Lengths are counted in characters for UTF-8 candidates, bytes otherwise
+    pub fn allows(&self, candidate: &[u8]) -> bool {
+        let (candidate, _) = to_text(candidate);
+        let len = candidate.chars().count();
+
+        if self.min_len.is_some_and(|min| len < min) {
+            return false;
+        }
+
+        if self.max_len.is_some_and(|max| len > max) {
+            return false;
+        }
+
+        self.require
+            .iter()
+            .all(|class| candidate.chars().any(|c| class.matches(c)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_filter() {
+        let filter =
+            CandidateFilter::new(Some(8), Some(12), vec![CharClass::Upper, CharClass::Digit]);
+        assert!(filter.allows(b"Password1"));
+        assert!(!filter.allows(b"password1"));
+        assert!(!filter.allows(b"Pass1"));
+        assert!(!filter.allows(b"Password1234567"));
+        assert!(filter.allows("Ünïcödé123".as_bytes()));
+        assert!(filter.allows(b"P\xe4sswort1"));
+
+        assert!(!CandidateFilter::default().is_active());
+        assert!(CandidateFilter::default().allows(b""));
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Number of lines between entries in the sparse offset table
+pub const INDEX_INTERVAL: u64 = 65_536;
+
+/// Bump when the on-disk layout changes so stale indexes are rebuilt
+const INDEX_VERSION: u32 = 1;
+
+/// Cached line count and sparse line -> byte offset table for a wordlist
+///
+/// Stored as a sidecar file next to the wordlist (`<wordlist>.idx`), or in
+/// the user's cache directory if the wordlist's directory is read-only. An
+/// index only applies to the exact file it was built from: path, size and
+/// modification time must all match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordlistIndex {
+    version: u32,
+    path: String,
+    size: u64,
+    mtime_nanos: u128,
+
+    /// Total number of lines, as reported by `WordlistReader::count_words`
+    pub total_lines: u64,
+
+    /// Byte offset of line `i * INDEX_INTERVAL` (empty for compressed wordlists)
+    pub offsets: Vec<u64>,
+}
+
+/// Identity of a wordlist file: canonical path, size and modification time
+fn file_key(path: &str) -> Option<(String, u64, u128)> {
+    let canonical = fs::canonicalize(path).ok()?;
+    let metadata = fs::metadata(&canonical).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    Some((
+        canonical.to_string_lossy().into_owned(),
+        metadata.len(),
+        mtime,
+    ))
+}
+
+/// Candidate locations for the index of a wordlist, in lookup order
+fn index_paths(canonical: &str) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(format!("{}.idx", canonical))];
+
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")));
+    if let Some(dir) = cache_dir {
+        let digest = hex::encode(Sha256::digest(canonical.as_bytes()));
+        paths.push(dir.join("pbkdf2_cracker").join(format!("{}.idx", digest)));
+    }
+
+    paths
+}
+
+impl WordlistIndex {
+    /// Create an index for a wordlist from a completed counting pass
+    pub fn new(path: &str, total_lines: u64, offsets: Vec<u64>) -> Option<Self> {
+        let (canonical, size, mtime_nanos) = file_key(path)?;
+        Some(Self {
+            version: INDEX_VERSION,
+            path: canonical,
+            size,
+            mtime_nanos,
+            total_lines,
+            offsets,
+        })
+    }
+
+    /// Load the index for a wordlist, if one exists and is still valid
+    pub fn load(path: &str) -> Option<Self> {
+        let key = file_key(path)?;
+
+        index_paths(&key.0).into_iter().find_map(|index_path| {
+            let file = File::open(index_path).ok()?;
+            let index: WordlistIndex = serde_json::from_reader(BufReader::new(file)).ok()?;
+            let matches = index.version == INDEX_VERSION
+                && (index.path.as_str(), index.size, index.mtime_nanos)
+                    == (key.0.as_str(), key.1, key.2);
+            matches.then_some(index)
+        })
+    }
+
+    /// Save the index next to the wordlist, falling back to the cache directory
+    /// Returns the path written, or `None` if no location was writable
+    pub fn save(&self) -> Option<PathBuf> {
+        index_paths(&self.path).into_iter().find(|index_path| {
+            if let Some(parent) = index_path.parent() {
+                if fs::create_dir_all(parent).is_err() {
+                    return false;
+                }
+            }
+            File::create(index_path)
+                .ok()
+                .and_then(|file| serde_json::to_writer(BufWriter::new(file), self).ok())
+                .is_some()
+        })
+    }
+
+    /// Closest indexed position at or before `line`, as (line, byte offset)
+    pub fn nearest(&self, line: u64) -> Option<(u64, u64)> {
+        let slot = (line / INDEX_INTERVAL) as usize;
+        let slot = slot.min(self.offsets.len().checked_sub(1)?);
+        Some((slot as u64 * INDEX_INTERVAL, self.offsets[slot]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_index_is_invalidated_by_changes() {
+        let path =
+            std::env::temp_dir().join(format!("pbkdf2_cracker_{}_index.txt", std::process::id()));
+        let path_str = path.to_string_lossy().into_owned();
+        File::create(&path).unwrap().write_all(b"a\nb\n").unwrap();
+
+        let index = WordlistIndex::new(&path_str, 2, vec![0]).unwrap();
+        let saved = index.save().unwrap();
+        assert_eq!(WordlistIndex::load(&path_str), Some(index.clone()));
+        assert_eq!(index.nearest(70_000), Some((0, 0)));
+
+        // Appending changes the size, so the stale index is ignored
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"c\n")
+            .unwrap();
+        assert_eq!(WordlistIndex::load(&path_str), None);
+
+        fs::remove_file(saved).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,21 @@
+pub mod association;
+pub mod attack;
+pub mod checkpoint;
+pub mod cli;
+pub mod cracker;
+pub mod dedup;
+pub mod encoding;
+pub mod errors;
+pub mod filter;
+pub mod index;
+pub mod markov;
+pub mod parser;
+pub mod prince;
+pub mod report;
+pub mod rulegen;
+pub mod rules;
+pub mod sessions;
+pub mod stats;
+pub mod verify;
+pub mod wordlist;
+pub mod wordprep;
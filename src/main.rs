@@ -1,35 +1,85 @@
-mod cli;
-mod cracker;
-mod parser;
-mod rules;
-mod wordlist;
-mod checkpoint;
-mod verify;
-mod stats;
-mod errors;
-
 use clap::Parser;
-use cli::Cli;
-use cracker::Pbkdf2Cracker;
-use errors::Result;
-use parser::ParsedHash;
-use rules::RuleEngine;
-use wordlist::WordlistReader;
-use checkpoint::{Checkpoint, CheckpointManager};
-use rayon::prelude::*;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use pbkdf2_cracker::association::{derive_words, HashTarget};
+use pbkdf2_cracker::attack::{Attack, CandidatePipeline, Position, Targets, CHUNK_SIZE};
+use pbkdf2_cracker::checkpoint::{
+    Checkpoint, CheckpointManager, FileFingerprint, Recovered, SessionState,
+};
+use pbkdf2_cracker::cli::{Cli, Command, SessionsCommand, TrainArgs, WordlistCommand};
+use pbkdf2_cracker::dedup::BloomFilter;
+use pbkdf2_cracker::encoding::format_candidate;
+use pbkdf2_cracker::errors::{CrackerError, Result};
+use pbkdf2_cracker::markov::{MarkovGenerator, MarkovModel};
+use pbkdf2_cracker::parser::ParsedHash;
+use pbkdf2_cracker::prince::PrinceGenerator;
+use pbkdf2_cracker::report::{self, ResumePoint, StatusEvent};
+use pbkdf2_cracker::rulegen::RuleGenerator;
+use pbkdf2_cracker::rules::RuleEngine;
+use pbkdf2_cracker::sessions;
+use pbkdf2_cracker::stats::format_duration;
+use pbkdf2_cracker::verify;
+use pbkdf2_cracker::wordlist::{
+    expand_wordlist_paths, Compression, WordEntry, WordSource, WordlistReader,
+};
+use pbkdf2_cracker::wordprep;
+use pbkdf2_cracker::{say, say_err};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 fn main() {
     let cli = Cli::parse();
+    report::configure(cli.output_mode(), cli.plain);
 
     // Validate arguments
     if let Err(e) = cli.validate() {
-        eprintln!("❌ Error: {}", e);
+        say_err!("❌ Error: {}", e);
         std::process::exit(1);
     }
 
+    // Handle wordlist preprocessing and Markov training
+    if let Some(command) = cli.command {
+        let result = match command {
+            Command::Wordlist(command) => run_wordlist_command(command),
+            Command::Train(args) => run_train_command(args),
+            Command::Sessions(command) => run_sessions_command(command),
+        };
+        if let Err(e) = result {
+            say_err!("❌ Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // A restored session is run with the arguments it was started with;
+    // the output options given with --restore replace the saved ones
+    let (cli, command) = match cli.restore {
+        Some(ref name) => match restore_session(name) {
+            Ok((restored, command)) => (
+                Cli {
+                    verbose: cli.verbose,
+                    quiet: cli.quiet,
+                    plain: cli.plain,
+                    status_json: cli.status_json,
+                    status_interval: cli.status_interval,
+                    ..restored
+                },
+                command,
+            ),
+            Err(e) => {
+                say_err!("❌ Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => {
+            let command = std::env::args()
+                .skip(1)
+                .filter(|arg| arg != "--resume")
+                .collect();
+            (cli, command)
+        }
+    };
+
     // Handle verification mode
     if cli.is_verify_mode() {
         let hash = cli.hash.unwrap();
@@ -39,83 +89,344 @@ fn main() {
     }
 
     // Run cracking mode
-    match run_cracker(cli) {
-        Ok(true) => {
-            std::process::exit(0);
-        }
-        Ok(false) => {
-            println!("\n😞 Password not found");
-            std::process::exit(1);
+    match run_cracker(cli, command) {
+        Ok(exit_code) => {
+            std::process::exit(exit_code);
         }
         Err(e) => {
-            eprintln!("\n❌ Error: {}", e);
+            say_err!("\n❌ Error: {}", e);
+            report::emit(&StatusEvent::Finish {
+                reason: "error".to_string(),
+                cracked: None,
+                hashes: None,
+                attempts: None,
+                elapsed_secs: None,
+                error: Some(e.to_string()),
+            });
             std::process::exit(1);
         }
     }
 }
 
-fn run_cracker(cli: Cli) -> Result<bool> {
-    let hash = cli.hash.unwrap();
-    let wordlist_path = cli.wordlist.unwrap();
+/// Exit code of a session stopped by Ctrl-C or SIGTERM, graceful or not
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Crack with the given options; `command` is recorded for `--restore`
+/// Returns the exit code: 0 if every hash was cracked, 1 if not, or
+/// `EXIT_INTERRUPTED`
+fn run_cracker(cli: Cli, command: Vec<String>) -> Result<i32> {
+    let filter = cli.candidate_filter();
+    let line_options = cli.line_options();
+    let wordlists = if cli.wordlist.is_empty() {
+        Vec::new()
+    } else {
+        expand_wordlist_paths(&cli.wordlist)?
+    };
 
     // Print banner
-    print_banner();
-
-    // Parse hash
-    println!("🔍 Parsing hash...");
-    let parsed_hash = ParsedHash::parse(&hash)?;
-    println!("   Iterations: {}", parsed_hash.iterations);
-    println!("   Salt: {}", String::from_utf8_lossy(&parsed_hash.salt));
-    println!("   Salt length: {} bytes", parsed_hash.salt.len());
-    println!("   Digest length: {} bytes", parsed_hash.digest.len());
-    println!();
-
-    // Load or create checkpoint
-    let (start_offset, _start_rule_index) = if cli.resume {
-        match Checkpoint::load(&cli.checkpoint) {
-            Ok(checkpoint) => {
-                println!("📂 Resuming from checkpoint:");
-                println!("   Wordlist offset: {}", checkpoint.wordlist_offset);
-                println!("   Total attempts: {}", checkpoint.total_attempts);
-                println!();
-                (checkpoint.wordlist_offset, checkpoint.rule_index)
+    report::banner();
+
+    // A Markov mask or PRINCE chains replace the wordlists as the session's
+    // single source
+    let generator = if let (Some(mask), Some(stats)) = (&cli.markov_mask, &cli.markov_stats) {
+        let model = MarkovModel::load(stats)?;
+        let markov = MarkovGenerator::new(&model, mask, cli.markov_threshold)?;
+        Some((format!("markov:{}", mask), Generator::Markov(markov)))
+    } else if cli.prince {
+        if wordlists.len() != 1 {
+            return Err(CrackerError::PrinceError(format!(
+                "PRINCE chains words of a single wordlist, got {}",
+                wordlists.len()
+            )));
+        }
+        let mut reader = WordlistReader::new(wordlists[0].clone())
+            .with_index(false)
+            .with_line_options(line_options);
+        let prince = PrinceGenerator::from_wordlist(&mut reader, &cli.prince_options())?;
+        Some((
+            format!("prince:{}", wordlists[0]),
+            Generator::Prince(prince),
+        ))
+    } else {
+        None
+    };
+    let sources: Vec<String> = match generator {
+        Some((ref label, _)) => vec![label.clone()],
+        None => wordlists.clone(),
+    };
+
+    // Parse hashes
+    let hash_targets = match (&cli.hash, &cli.hash_file) {
+        (_, Some(path)) => {
+            say!("🔍 Loading hashes from {}...", path);
+            let targets = HashTarget::load_file(path)?;
+            say!("   Hashes: {}", targets.len());
+            let with_fields = targets.iter().filter(|t| !t.fields.is_empty()).count();
+            say!("   With account fields: {}", with_fields);
+            targets
+        }
+        (Some(hash), None) => {
+            say!("🔍 Parsing hash...");
+            let parsed_hash = ParsedHash::parse(hash)?;
+            say!("   Iterations: {}", parsed_hash.iterations);
+            say!("   Salt: {}", String::from_utf8_lossy(&parsed_hash.salt));
+            say!("   Salt length: {} bytes", parsed_hash.salt.len());
+            say!("   Digest length: {} bytes", parsed_hash.digest.len());
+            vec![HashTarget {
+                label: "hash".to_string(),
+                hash: parsed_hash,
+                fields: Vec::new(),
+            }]
+        }
+        (None, None) => unreachable!("validated by Cli::validate"),
+    };
+    say!();
+
+    // A named session keeps its checkpoint in the state directory
+    let checkpoint_path = match cli.session {
+        Some(ref name) => sessions::session_path(&sessions::sessions_dir()?, name)?,
+        None => cli.checkpoint.clone(),
+    };
+
+    // Load checkpoint
+    let checkpoint = if cli.resume && !sources.is_empty() {
+        // A corrupt checkpoint is an error, not a fresh start
+        match Checkpoint::recover(&checkpoint_path)? {
+            Recovered::Latest(checkpoint) => Some(checkpoint),
+            Recovered::Backup { checkpoint, error } => {
+                say_err!("⚠  {}", error);
+                say_err!(
+                    "   Resuming from the previous checkpoint ({})",
+                    Checkpoint::backup_path(&checkpoint_path)
+                );
+                say!();
+                Some(checkpoint)
             }
-            Err(_) => {
-                println!("⚠  No checkpoint found, starting from beginning");
-                println!();
-                (0, 0)
+            Recovered::Missing => {
+                say_err!("⚠  No checkpoint found, starting from beginning");
+                say!();
+                None
             }
         }
     } else {
-        (0, 0)
+        None
     };
 
     // Load rule engine
-    println!("📋 Loading rules...");
-    let rule_engine = if let Some(rules_path) = cli.rules {
-        RuleEngine::from_file(&rules_path)?
+    say!("📋 Loading rules...");
+    let mut rule_engine = if let Some(ref rules_path) = cli.rules {
+        RuleEngine::from_file(rules_path)?
     } else if cli.default_rules {
         RuleEngine::default_rules()
+    } else if cli.leet_rules {
+        RuleEngine::leet_rules()
     } else {
         RuleEngine::new()
     };
-    println!("   Loaded {} rules", rule_engine.count());
-    println!();
-
-    // Set up wordlist
-    println!("📖 Loading wordlist: {}", wordlist_path);
-    let mut wordlist_reader = WordlistReader::from_offset(wordlist_path.clone(), start_offset);
-    let total_words = wordlist_reader.count_words()?;
-    println!("   Total words: {}", total_words);
-    if start_offset > 0 {
-        println!("   Starting from offset: {}", start_offset);
+    let mut rule_seed = None;
+    if let Some(count) = cli.generate_rules {
+        // Without an explicit seed, reuse the resumed session's seed, or pick
+        // one and show it so the run can be repeated
+        let saved_seed = checkpoint
+            .as_ref()
+            .and_then(|checkpoint| checkpoint.session.as_ref())
+            .and_then(|session| session.options.get("rule-seed"))
+            .and_then(|seed| seed.parse().ok());
+        let seed = cli.rule_seed.or(saved_seed).unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0)
+        });
+        let generated = RuleGenerator::new(seed).generate(count);
+        say!(
+            "   Generated {} random rules (seed: {})",
+            generated.len(),
+            seed
+        );
+        rule_engine.add_rules(generated);
+        rule_seed = Some(seed);
+    }
+    say!("   Loaded {} rules", rule_engine.count());
+    say!();
+
+    let session = session_state(
+        &cli,
+        &hash_targets,
+        &wordlists,
+        generator.as_ref().map(|(_, generator)| generator),
+        &rule_engine,
+        rule_seed,
+        command,
+    )?;
+
+    // Resume only where the checkpoint's session matches this one
+    let (start_file, start_offset, start_byte_offset, start_rule_index) = match checkpoint {
+        Some(ref checkpoint) => {
+            let mismatches = checkpoint.mismatches(&session);
+            if !mismatches.is_empty() {
+                let reasons: Vec<String> =
+                    mismatches.iter().map(|m| format!("   - {}", m)).collect();
+                return Err(CrackerError::CheckpointError(format!(
+                    "{} was written for a different session:\n{}\n   Restore the original options, or run without --resume to start over",
+                    checkpoint_path,
+                    reasons.join("\n")
+                )));
+            }
+            let start_file = checkpoint.resume_file(&sources).ok_or_else(|| {
+                CrackerError::CheckpointError(format!(
+                    "Checkpoint source {} is not among this session's sources",
+                    checkpoint.wordlist_path.as_deref().unwrap_or("(unknown)")
+                ))
+            })?;
+            say!("📂 Resuming from checkpoint:");
+            if checkpoint.session.is_none() {
+                say!("   ⚠  Written by an older version; targets and inputs can't be checked");
+            }
+            say!(
+                "   Wordlist: {} ({} of {})",
+                sources[start_file],
+                start_file + 1,
+                sources.len()
+            );
+            say!("   Wordlist offset: {}", checkpoint.wordlist_offset);
+            if let Some(byte_offset) = checkpoint.byte_offset {
+                say!("   Byte offset: {}", byte_offset);
+            }
+            say!("   Total attempts: {}", checkpoint.total_attempts);
+            if checkpoint.elapsed_secs > 0.0 {
+                say!(
+                    "   Elapsed: {}",
+                    format_duration(Duration::from_secs_f64(checkpoint.elapsed_secs))
+                );
+            }
+            say!();
+            (
+                start_file,
+                checkpoint.wordlist_offset,
+                checkpoint.byte_offset,
+                checkpoint.rule_index,
+            )
+        }
+        None => (0, 0, None, 0),
+    };
+
+    let global_dedup = cli.dedup_memory.map(BloomFilter::with_memory_mb);
+    let pipeline = CandidatePipeline::new(rule_engine, filter, global_dedup);
+
+    // Set up wordlists; together they form one keyspace, read in order
+    let open_reader = |file: usize| {
+        let (line, byte_offset) = if file == start_file {
+            (start_offset, start_byte_offset)
+        } else {
+            (0, None)
+        };
+        WordlistReader::from_position(wordlists[file].clone(), line, byte_offset)
+            .with_index(!cli.no_index)
+            .with_line_options(line_options)
+    };
+    // Words in each source, for the progress recorded in checkpoints
+    let mut source_sizes = match generator {
+        Some((_, Generator::Markov(ref markov))) => vec![saturating_u64(markov.keyspace())],
+        Some((_, Generator::Prince(ref prince))) => vec![saturating_u64(prince.keyspace())],
+        None => Vec::new(),
+    };
+    if !wordlists.is_empty() && generator.is_none() {
+        if wordlists.len() == 1 {
+            say!("📖 Loading wordlist: {}", wordlists[0]);
+        } else {
+            say!("📖 Loading {} wordlists:", wordlists.len());
+        }
+        let mut total_words = 0;
+        let mut all_cached = true;
+        source_sizes.clear();
+        for (file, path) in wordlists.iter().enumerate() {
+            let reader = open_reader(file);
+            let compression = reader.compression()?;
+            all_cached &= reader.has_index();
+            let words = reader.count_words()?;
+            total_words += words;
+            source_sizes.push(words);
+            if wordlists.len() > 1 {
+                if compression != Compression::None {
+                    say!(
+                        "   [{}] {}: {} words ({})",
+                        file + 1,
+                        path,
+                        words,
+                        compression
+                    );
+                } else {
+                    say!("   [{}] {}: {} words", file + 1, path, words);
+                }
+            } else if compression != Compression::None {
+                say!("   Compression: {} (streaming)", compression);
+            }
+        }
+        if all_cached {
+            say!("   Total words: {} (from index)", total_words);
+        } else {
+            say!("   Total words: {}", total_words);
+        }
+        if start_offset > 0 {
+            say!("   Starting from offset: {}", start_offset);
+        }
+        say!();
+    }
+    match generator {
+        Some((_, Generator::Markov(ref markov))) => {
+            say!(
+                "🎲 Markov mask: {}",
+                cli.markov_mask.as_deref().unwrap_or_default()
+            );
+            say!(
+                "   Statistics: {}",
+                cli.markov_stats.as_deref().unwrap_or_default()
+            );
+            if cli.markov_threshold > 0 {
+                say!("   Threshold: {} per position", cli.markov_threshold);
+            }
+            say!("   Keyspace: {}", markov.keyspace());
+        }
+        Some((_, Generator::Prince(ref prince))) => {
+            let options = cli.prince_options();
+            say!("🔗 PRINCE elements from: {}", wordlists[0]);
+            say!("   Elements: {}", prince.element_count());
+            say!(
+                "   Elements per candidate: {}-{}",
+                options.min_elements,
+                options.max_elements
+            );
+            say!("   Length: {}-{} bytes", options.min_len, options.max_len);
+            say!("   Chains: {}", prince.chain_count());
+            say!("   Keyspace: {}", prince.keyspace());
+        }
+        None => {}
+    }
+    if generator.is_some() {
+        if start_offset > 0 {
+            say!("   Starting from position: {}", start_offset);
+        }
+        say!();
     }
-    println!();
 
-    println!("🎯 Attack configuration:");
-    println!("   Threads: {}", cli.threads);
-    println!("   Chunk size: 1000");
-    println!();
+    say!("🎯 Attack configuration:");
+    say!("   Threads: {}", cli.threads);
+    say!("   Chunk size: {}", CHUNK_SIZE);
+    if let Some(min) = cli.min_len {
+        say!("   Minimum length: {}", min);
+    }
+    if let Some(max) = cli.max_len {
+        say!("   Maximum length: {}", max);
+    }
+    if let Some(megabytes) = cli.dedup_memory {
+        say!("   Global dedup filter: {} MB", megabytes);
+    }
+    if !cli.require.is_empty() {
+        let classes: Vec<String> = cli.require.iter().map(|c| c.to_string()).collect();
+        say!("   Required classes: {}", classes.join(", "));
+    }
+    say!();
 
     // Set thread pool
     rayon::ThreadPoolBuilder::new()
@@ -123,165 +434,629 @@ fn run_cracker(cli: Cli) -> Result<bool> {
         .build_global()
         .unwrap();
 
-    // Create cracker (shared across threads)
-    let cracker = Arc::new(Pbkdf2Cracker::new(parsed_hash));
+    // Progress is measured in (word, rule) pairs left to test
+    let remaining_words = source_sizes
+        .iter()
+        .skip(start_file)
+        .sum::<u64>()
+        .saturating_sub(start_offset);
+    let keyspace = remaining_words
+        .saturating_mul(pipeline.rule_engine.count() as u64)
+        .saturating_sub(start_rule_index as u64);
 
-    // Stats
-    let attempts = Arc::new(AtomicU64::new(0));
-    let found = Arc::new(AtomicBool::new(false));
-    let found_password = Arc::new(std::sync::Mutex::new(String::new()));
+    // Crackers for every hash (shared across threads)
+    let attack = Attack::new(pipeline, Targets::new(&hash_targets)).with_keyspace(keyspace);
 
-    // Checkpoint manager
-    let mut checkpoint_mgr = CheckpointManager::new(cli.checkpoint.clone(), 10000);
+    // The first Ctrl-C or SIGTERM lets the candidates in flight finish and
+    // saves a final checkpoint; a second one quits at once
+    let stop = attack.stop_flag();
+    let handler = ctrlc::set_handler(move || {
+        if stop.swap(true, Ordering::SeqCst) {
+            say_err!("\n⚠  Forced quit; progress since the last checkpoint is lost");
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        say_err!("\n⏹  Stopping after the current candidates (press Ctrl-C again to quit now)...");
+    });
+    if let Err(e) = handler {
+        say_err!("⚠  Could not install the Ctrl-C handler: {}", e);
+    }
 
-    println!("🚀 Starting password cracking...");
-    println!();
+    // Checkpoint manager
+    let attack_mode = session.attack_mode.clone();
+    let mut checkpoint_mgr = CheckpointManager::new(
+        checkpoint_path.clone(),
+        Duration::from_secs(cli.checkpoint_interval),
+    )
+    .with_resumed(checkpoint.as_ref())
+    .with_session(session)
+    .with_source_sizes(source_sizes.clone());
 
-    let start_time = Instant::now();
-    let mut last_report = Instant::now();
+    report::emit(&StatusEvent::Start {
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        hashes: hash_targets.iter().map(|t| t.hash.to_string()).collect(),
+        sources: sources.clone(),
+        attack_mode,
+        rules: attack.pipeline.rule_engine.count(),
+        threads: cli.threads,
+        keyspace,
+        checkpoint: checkpoint_path.clone(),
+        resumed_from: checkpoint.as_ref().map(|_| ResumePoint {
+            source: sources[start_file].clone(),
+            line: start_offset,
+            rule_index: start_rule_index,
+        }),
+    });
 
-    // Read wordlist
-    let mut words_iter = wordlist_reader.read_words()?;
+    say!("🚀 Starting password cracking...");
+    say!();
 
-    // Collect words in chunks for better parallelism
-    let mut chunk = Vec::new();
-    const CHUNK_SIZE: usize = 1000;
+    let start_time = Instant::now();
+    let earlier = checkpoint
+        .as_ref()
+        .map_or((0, 0.0), |c| (c.total_attempts, c.elapsed_secs));
+    let status_interval = Duration::from_secs(cli.status_interval);
+    let finished = AtomicBool::new(false);
 
-    while let Some(word_result) = words_iter.next() {
-        if found.load(Ordering::Relaxed) {
-            break;
+    // In the JSON mode a reporter thread streams cracks and progress
+    let interrupted = std::thread::scope(|scope| {
+        if !report::is_human() {
+            scope.spawn(|| {
+                report_status(&attack, &hash_targets, earlier, status_interval, &finished)
+            });
         }
 
-        let (offset, base_word) = word_result?;
-        chunk.push((offset, base_word));
+        let interrupted = (|| -> Result<Option<(String, Position)>> {
+            let mut interrupted = None;
 
-        // Process chunk when full
-        if chunk.len() >= CHUNK_SIZE {
-            let result = process_chunk(
-                &chunk,
-                &cracker,
-                &rule_engine,
-                &attempts,
-                &found,
-                &found_password,
-            );
-
-            // Report progress
-            let now = Instant::now();
-            if now.duration_since(last_report) >= Duration::from_secs(2) {
-                let elapsed = start_time.elapsed().as_secs_f64();
-                let total_attempts = attempts.load(Ordering::Relaxed);
-                let speed = total_attempts as f64 / elapsed;
-                print!(
-                    "\r[+] Attempts: {:>10} | Elapsed: {:>6.1}s | Speed: {:>8.2} H/s",
-                    total_attempts, elapsed, speed
+            // Each hash is tried against its own account words before the wordlist
+            if cli.association {
+                say!("🔗 Testing each hash against its own account words...");
+                for (index, target) in hash_targets.iter().enumerate() {
+                    let words = derive_words(&target.fields, cli.domain.as_deref());
+                    let chunk: Vec<WordEntry> = words
+                        .into_iter()
+                        .enumerate()
+                        .map(|(line, word)| WordEntry {
+                            line: line as u64,
+                            byte_offset: 0,
+                            word: Cow::Owned(word),
+                        })
+                        .collect();
+                    attack.test_account_words(index, &chunk);
+                }
+                say!(
+                    "   Cracked {} of {} from account words",
+                    attack.targets.cracked_count(),
+                    attack.targets.len()
                 );
-                std::io::Write::flush(&mut std::io::stdout()).unwrap();
-                last_report = now;
+                say!();
             }
 
-            // Save checkpoint
-            let _ = checkpoint_mgr.maybe_save(offset, 0, attempts.load(Ordering::Relaxed));
+            for file in start_file..sources.len() {
+                if attack.targets.all_cracked() {
+                    break;
+                }
+                let wordlist_path = &sources[file];
+                if file > start_file {
+                    attack.stats().suspend(|| {
+                        say!(
+                            "📖 Wordlist {} of {}: {}",
+                            file + 1,
+                            sources.len(),
+                            wordlist_path
+                        )
+                    });
+                }
+
+                let (line, byte_offset, first_rule) = if file == start_file {
+                    (start_offset, start_byte_offset, start_rule_index)
+                } else {
+                    (0, None, 0)
+                };
+                let mut wordlist_reader;
+                let mapped;
+                let words_iter: Box<dyn Iterator<Item = Result<WordEntry>>> = match generator {
+                    // Generated candidates use their position as line number
+                    Some((_, ref generator)) => Box::new(
+                        generator
+                            .candidates_from(line as u128)
+                            .map(|(position, word)| {
+                                Ok(WordEntry {
+                                    line: u64::try_from(position).unwrap_or(u64::MAX),
+                                    byte_offset: 0,
+                                    word: Cow::Owned(word),
+                                })
+                            }),
+                    ),
+                    // Read wordlist
+                    // Plain files are memory-mapped and read without copying; compressed
+                    // wordlists are streamed
+                    None => {
+                        wordlist_reader = open_reader(file);
+                        mapped = wordlist_reader.map()?;
+                        let words = match mapped {
+                            Some(ref mapped) => {
+                                WordSource::Mapped(mapped.words_from(line, byte_offset))
+                            }
+                            None => WordSource::Streamed(wordlist_reader.read_words()?),
+                        };
+                        if line > 0 && words.seeked() {
+                            say!("⏩ Seeked to byte offset {}", byte_offset.unwrap_or(0));
+                            say!();
+                        }
+                        Box::new(words)
+                    }
+                };
 
-            chunk.clear();
+                // Lines the reader skips (empty ones, by default) are part of the
+                // keyspace too; a finished source moves the bar to its end
+                let source_end = attack.stats().progress().map(|done| {
+                    let pairs = source_sizes[file]
+                        .saturating_sub(line)
+                        .saturating_mul(attack.pipeline.rule_engine.count() as u64);
+                    done + pairs.saturating_sub(first_rule as u64)
+                });
 
-            if result {
-                break;
+                // Checkpoints record the first word and rule not yet fully tested
+                let stopped = attack.run(words_iter, first_rule, |attack, position| {
+                    let saved = checkpoint_mgr.maybe_save(
+                        file,
+                        wordlist_path,
+                        position.line,
+                        generator.is_none().then_some(position.byte_offset),
+                        position.rule_index,
+                        attack.attempts(),
+                    );
+                    match saved {
+                        Ok(true) => report::emit(&StatusEvent::Checkpoint {
+                            path: checkpoint_path.clone(),
+                            source: wordlist_path.clone(),
+                            line: position.line,
+                            rule_index: position.rule_index,
+                            attempts: checkpoint_mgr.total_attempts(attack.attempts()),
+                        }),
+                        Ok(false) => {}
+                        Err(e) => attack
+                            .stats()
+                            .suspend(|| say_err!("⚠  Checkpoint not saved: {}", e)),
+                    }
+                })?;
+                if let Some(position) = stopped {
+                    if attack.is_stopped() && !attack.targets.all_cracked() {
+                        checkpoint_mgr.save(
+                            file,
+                            wordlist_path,
+                            position.line,
+                            generator.is_none().then_some(position.byte_offset),
+                            position.rule_index,
+                            attack.attempts(),
+                        )?;
+                        report::emit(&StatusEvent::Checkpoint {
+                            path: checkpoint_path.clone(),
+                            source: wordlist_path.clone(),
+                            line: position.line,
+                            rule_index: position.rule_index,
+                            attempts: checkpoint_mgr.total_attempts(attack.attempts()),
+                        });
+                        interrupted = Some((wordlist_path.clone(), position));
+                    }
+                    break;
+                }
+                if let (Some(end), Some(done)) = (source_end, attack.stats().progress()) {
+                    attack.stats().advance(end.saturating_sub(done));
+                }
             }
-        }
+
+            Ok(interrupted)
+        })();
+        finished.store(true, Ordering::SeqCst);
+        interrupted
+    })?;
+
+    if interrupted.is_some() {
+        attack.stats().abandon();
+    } else {
+        attack.stats().finish();
+    }
+
+    if let Some(ref path) = cli.save_hit_rules {
+        attack.pipeline.save_hit_rules(path)?;
     }
 
-    // Process remaining chunk
-    if !chunk.is_empty() && !found.load(Ordering::Relaxed) {
-        process_chunk(
-            &chunk,
-            &cracker,
-            &rule_engine,
-            &attempts,
-            &found,
-            &found_password,
+    let finish = |reason: &str| {
+        report::emit(&StatusEvent::Finish {
+            reason: reason.to_string(),
+            cracked: Some(attack.targets.cracked_count()),
+            hashes: Some(attack.targets.len()),
+            attempts: Some(checkpoint_mgr.total_attempts(attack.attempts())),
+            elapsed_secs: Some(checkpoint_mgr.total_elapsed().as_secs_f64()),
+            error: None,
+        })
+    };
+    if interrupted.is_some() {
+        finish("interrupted");
+    } else if attack.targets.all_cracked() {
+        finish("all_cracked");
+    } else {
+        finish("exhausted");
+    }
+
+    if let Some((source, position)) = interrupted {
+        say!();
+        say!("⏹  Interrupted");
+        for target in &attack.targets.targets {
+            if let Some(ref candidate) = *target.cracked.lock().unwrap() {
+                say!(
+                    "   🔓 {}: {}",
+                    target.label,
+                    format_candidate(&candidate.password)
+                );
+            }
+        }
+        attack.stats().print_summary();
+        say!(
+            "⏱  Session total: {} attempts in {}",
+            checkpoint_mgr.total_attempts(attack.attempts()),
+            format_duration(checkpoint_mgr.total_elapsed())
         );
+        say!("💾 Checkpoint saved to {}", checkpoint_path);
+        say!(
+            "   Next: {} line {}, rule {}",
+            source,
+            position.line,
+            position.rule_index
+        );
+        match cli.session {
+            Some(ref name) => say!("   Run again with --restore {} to continue", name),
+            None => say!("   Run again with --resume to continue"),
+        }
+        return Ok(EXIT_INTERRUPTED);
     }
 
-    let elapsed = start_time.elapsed();
-    let total_attempts = attempts.load(Ordering::Relaxed);
+    // Attempts and time cover the whole session, including resumed runs
+    let speed = attack.attempts() as f64 / start_time.elapsed().as_secs_f64();
+    let elapsed = checkpoint_mgr.total_elapsed();
+    let total_attempts = checkpoint_mgr.total_attempts(attack.attempts());
+    let Attack {
+        pipeline, targets, ..
+    } = attack;
 
     // Print results
-    if found.load(Ordering::Relaxed) {
-        let password = found_password.lock().unwrap();
-        println!("\n");
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("🔥 PASSWORD FOUND 🔥");
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("   Password: {}", password);
-        println!("   Attempts: {}", total_attempts);
-        println!("   Time: {:.2}s", elapsed.as_secs_f64());
-        println!("   Speed: {:.2} H/s", total_attempts as f64 / elapsed.as_secs_f64());
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-
-        // Delete checkpoint on success
-        let _ = Checkpoint::delete(&cli.checkpoint);
-
-        Ok(true)
+    let all_cracked = targets.all_cracked();
+    if targets.len() == 1 {
+        let cracked = targets.targets[0].cracked.lock().unwrap().take();
+        if let Some(candidate) = cracked {
+            say!();
+            say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            report::headline("🔥", "PASSWORD FOUND");
+            say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            say!("   Password: {}", format_candidate(&candidate.password));
+            say!("   Base word: {}", format_candidate(&candidate.base));
+            say!(
+                "   Rule: {}",
+                pipeline.rule_engine.rules()[candidate.rule_index]
+            );
+            say!("   Attempts: {}", total_attempts);
+            say!("   Time: {}", format_duration(elapsed));
+            say!("   Speed: {:.2} H/s", speed);
+            pipeline.print_savings();
+            say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        } else {
+            say!();
+            say!("   Total attempts: {}", total_attempts);
+            say!("   Time: {}", format_duration(elapsed));
+            pipeline.print_savings();
+            say!("\n😞 Password not found");
+        }
     } else {
-        println!("\n");
-        println!("   Total attempts: {}", total_attempts);
-        println!("   Time: {:.2}s", elapsed.as_secs_f64());
-        Ok(false)
+        say!();
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        report::headline(
+            "🔥",
+            &format!(
+                "CRACKED {} OF {} HASHES",
+                targets.cracked_count(),
+                targets.len()
+            ),
+        );
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        for target in &targets.targets {
+            if let Some(ref candidate) = *target.cracked.lock().unwrap() {
+                say!(
+                    "   {}: {} (base word: {}, rule: {})",
+                    target.label,
+                    format_candidate(&candidate.password),
+                    format_candidate(&candidate.base),
+                    pipeline.rule_engine.rules()[candidate.rule_index]
+                );
+            }
+        }
+        say!("   Attempts: {}", total_attempts);
+        say!("   Time: {}", format_duration(elapsed));
+        say!("   Speed: {:.2} H/s", speed);
+        pipeline.print_savings();
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        if !all_cracked {
+            say!(
+                "\n😞 {} of {} hashes not cracked",
+                targets.len() - targets.cracked_count(),
+                targets.len()
+            );
+        }
+    }
+
+    // Delete checkpoint on success
+    if all_cracked {
+        let _ = Checkpoint::delete(&checkpoint_path);
     }
+
+    Ok(if all_cracked { 0 } else { 1 })
 }
 
-fn process_chunk(
-    chunk: &[(u64, String)],
-    cracker: &Arc<Pbkdf2Cracker>,
-    rule_engine: &RuleEngine,
-    attempts: &Arc<AtomicU64>,
-    found: &Arc<AtomicBool>,
-    found_password: &Arc<std::sync::Mutex<String>>,
-) -> bool {
-    // Generate all candidates from chunk
-    let candidates: Vec<(String, String)> = chunk
-        .iter()
-        .flat_map(|(_offset, word)| {
-            rule_engine
-                .generate_candidates(word)
-                .into_iter()
-                .map(|candidate| (word.clone(), candidate))
-        })
-        .collect();
-
-    // Test in parallel
-    let result = candidates
-        .par_iter()
-        .find_any(|(_base, candidate)| {
-            if found.load(Ordering::Relaxed) {
-                return false;
+/// Run a `wordlist` preprocessing subcommand and print what it did
+fn run_wordlist_command(command: WordlistCommand) -> Result<()> {
+    let (stats, action) = match command {
+        WordlistCommand::Merge(args) => {
+            let stats = wordprep::merge(&args.inputs, &args.output, &args.prep_options())?;
+            (stats, format!("Merged into {}", args.output))
+        }
+        WordlistCommand::Dedup(args) => {
+            let stats = wordprep::dedup(&args.inputs, &args.output, &args.prep_options())?;
+            (stats, format!("Deduplicated into {}", args.output))
+        }
+        WordlistCommand::Sort(args) => {
+            let options = args.prep_options();
+            let stats = wordprep::sort_by_frequency(&args.inputs, &args.output, &options)?;
+            (stats, format!("Sorted by frequency into {}", args.output))
+        }
+        WordlistCommand::Split { args, shards } => {
+            let options = args.prep_options();
+            let stats = wordprep::split(&args.inputs, &args.output, shards, &options)?;
+            let first = wordprep::shard_path(&args.output, 0);
+            let last = wordprep::shard_path(&args.output, shards - 1);
+            (stats, format!("Split into {} .. {}", first, last))
+        }
+    };
+
+    say!("✅ {}", action);
+    say!("   Words read: {}", stats.read);
+    if stats.filtered > 0 {
+        say!("   Filtered out: {}", stats.filtered);
+    }
+    let duplicates = stats.read - stats.filtered - stats.written;
+    if duplicates > 0 {
+        say!("   Duplicates removed: {}", duplicates);
+    }
+    say!("   Words written: {}", stats.written);
+    Ok(())
+}
+
+fn run_train_command(args: TrainArgs) -> Result<()> {
+    let mut model = MarkovModel::new();
+    let words = model.train_wordlists(&args.inputs, args.line_options())?;
+    model.save(&args.output)?;
+
+    say!("✅ Trained on {} words, saved to {}", words, args.output);
+    Ok(())
+}
+
+/// Stream cracks as they happen and progress every `interval` until `finished`
+fn report_status(
+    attack: &Attack,
+    hash_targets: &[HashTarget],
+    (earlier_attempts, earlier_secs): (u64, f64),
+    interval: Duration,
+    finished: &AtomicBool,
+) {
+    let stats = attack.stats();
+    let mut reported = vec![false; attack.targets.len()];
+    let mut last_progress = Instant::now();
+    loop {
+        // Checked first, so cracks made before finishing are still reported
+        let done = finished.load(Ordering::SeqCst);
+        for (index, target) in attack.targets.targets.iter().enumerate() {
+            if reported[index] {
+                continue;
             }
+            if let Some(ref candidate) = *target.cracked.lock().unwrap() {
+                report::emit(&StatusEvent::Cracked {
+                    index,
+                    label: target.label.clone(),
+                    hash: hash_targets[index].hash.to_string(),
+                    password: format_candidate(&candidate.password).into_owned(),
+                    base_word: format_candidate(&candidate.base).into_owned(),
+                    rule: attack.pipeline.rule_engine.rules()[candidate.rule_index].to_string(),
+                });
+                reported[index] = true;
+            }
+        }
+        if done {
+            return;
+        }
+
+        if last_progress.elapsed() >= interval {
+            let position = stats.progress().unwrap_or(0);
+            report::emit(&StatusEvent::Progress {
+                attempts: earlier_attempts + attack.attempts(),
+                rate: stats.rate(),
+                position,
+                keyspace: stats.keyspace().unwrap_or(0),
+                eta_secs: stats
+                    .eta()
+                    .filter(|_| position > 0)
+                    .map(|eta| eta.as_secs_f64()),
+                elapsed_secs: earlier_secs + stats.elapsed().as_secs_f64(),
+                word: stats.message(),
+            });
+            last_progress = Instant::now();
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
 
-            let is_match = cracker.test_password(candidate);
-            attempts.fetch_add(1, Ordering::Relaxed);
+/// Load a named session and parse the arguments it was started with
+fn restore_session(name: &str) -> Result<(Cli, Vec<String>)> {
+    let checkpoint = sessions::load_session(&sessions::sessions_dir()?, name)?;
+    let session = checkpoint
+        .session
+        .filter(|session| !session.command.is_empty())
+        .ok_or_else(|| {
+            CrackerError::SessionError(format!(
+                "Session '{}' was saved without its command and can't be restored",
+                name
+            ))
+        })?;
 
-            if is_match {
-                found.store(true, Ordering::Relaxed);
-                let mut pwd = found_password.lock().unwrap();
-                *pwd = candidate.to_string();
-                true
-            } else {
-                false
+    // Relative paths are relative to where the session was started
+    if let Some(ref dir) = session.working_dir {
+        std::env::set_current_dir(dir)
+            .map_err(|e| CrackerError::SessionError(format!("Failed to enter {}: {}", dir, e)))?;
+    }
+
+    let args = std::iter::once("pbkdf2_cracker".to_string())
+        .chain(session.command.iter().cloned())
+        .chain(std::iter::once("--resume".to_string()));
+    let cli = Cli::try_parse_from(args).map_err(|e| {
+        CrackerError::SessionError(format!("Session '{}' has invalid arguments: {}", name, e))
+    })?;
+    cli.validate().map_err(|e| {
+        CrackerError::SessionError(format!("Session '{}' has invalid arguments: {}", name, e))
+    })?;
+    Ok((cli, session.command))
+}
+
+/// Run a `sessions` subcommand
+fn run_sessions_command(command: SessionsCommand) -> Result<()> {
+    let dir = sessions::sessions_dir()?;
+    match command {
+        SessionsCommand::List => {
+            let sessions = sessions::list_sessions(&dir)?;
+            if sessions.is_empty() {
+                say!("No saved sessions in {}", dir.display());
+                return Ok(());
             }
-        });
+            say!("📋 Sessions in {}:", dir.display());
+            for session in sessions {
+                report::result(format_args!("{}", session.name));
+                let progress = session
+                    .progress
+                    .map(|percent| format!("{:.1}%", percent))
+                    .unwrap_or_else(|| "?".to_string());
+                say!(
+                    "   {}  {}  {}  {}",
+                    session.name,
+                    progress,
+                    session.updated,
+                    session.target
+                );
+            }
+        }
+        SessionsCommand::Delete { name } => {
+            sessions::delete_session(&dir, &name)?;
+            say!("🗑  Deleted session {}", name);
+        }
+    }
+    Ok(())
+}
 
-    result.is_some()
+/// Record what the checkpointed position depends on
+fn session_state(
+    cli: &Cli,
+    hash_targets: &[HashTarget],
+    wordlists: &[String],
+    generator: Option<&Generator>,
+    rule_engine: &RuleEngine,
+    rule_seed: Option<u64>,
+    command: Vec<String>,
+) -> Result<SessionState> {
+    let mut options = BTreeMap::new();
+    if let Some(min) = cli.min_len {
+        options.insert("min-len".to_string(), min.to_string());
+    }
+    if let Some(max) = cli.max_len {
+        options.insert("max-len".to_string(), max.to_string());
+    }
+    if !cli.require.is_empty() {
+        let classes: Vec<String> = cli.require.iter().map(|c| c.to_string()).collect();
+        options.insert("require".to_string(), classes.join(","));
+    }
+    if cli.trim_whitespace {
+        options.insert("trim-whitespace".to_string(), "true".to_string());
+    }
+    if cli.keep_empty {
+        options.insert("keep-empty".to_string(), "true".to_string());
+    }
+    if let Some(seed) = rule_seed {
+        options.insert("rule-seed".to_string(), seed.to_string());
+    }
+
+    let attack_mode = match generator {
+        Some(Generator::Markov(_)) => {
+            let stats = cli.markov_stats.as_deref().unwrap_or_default();
+            let fingerprint = FileFingerprint::of(stats)?;
+            options.insert(
+                "markov-mask".to_string(),
+                cli.markov_mask.clone().unwrap_or_default(),
+            );
+            options.insert(
+                "markov-stats".to_string(),
+                format!("{} ({})", stats, &fingerprint.fingerprint[..16]),
+            );
+            options.insert(
+                "markov-threshold".to_string(),
+                cli.markov_threshold.to_string(),
+            );
+            "markov"
+        }
+        Some(Generator::Prince(_)) => {
+            let prince = cli.prince_options();
+            options.insert(
+                "prince-min-elements".to_string(),
+                prince.min_elements.to_string(),
+            );
+            options.insert(
+                "prince-max-elements".to_string(),
+                prince.max_elements.to_string(),
+            );
+            options.insert("prince-min-len".to_string(), prince.min_len.to_string());
+            options.insert("prince-max-len".to_string(), prince.max_len.to_string());
+            "prince"
+        }
+        None => "wordlist",
+    };
+
+    Ok(SessionState {
+        hashes: hash_targets.iter().map(|t| t.hash.to_string()).collect(),
+        wordlists: wordlists
+            .iter()
+            .map(|path| FileFingerprint::of(path))
+            .collect::<Result<_>>()?,
+        rules_digest: SessionState::rules_digest(rule_engine.rules()),
+        attack_mode: attack_mode.to_string(),
+        options,
+        command,
+        working_dir: std::env::current_dir()
+            .ok()
+            .map(|dir| dir.to_string_lossy().into_owned()),
+    })
+}
+
+fn saturating_u64(value: u128) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
 }
 
-fn print_banner() {
-    println!();
-    println!("╔══════════════════════════════════════════════════╗");
-    println!("║                                                  ║");
-    println!("║    PBKDF2-SHA256 Password Cracker v1.0.0         ║");
-    println!("║    Flask/Werkzeug Hash Cracker by Mikro          ║");
-    println!("║                                                  ║");
-    println!("║                                                  ║");
-    println!("╚══════════════════════════════════════════════════╝");
-    println!();
+/// Candidates generated instead of read from a wordlist
+enum Generator {
+    Markov(MarkovGenerator),
+    Prince(PrinceGenerator),
+}
+
+impl Generator {
+    /// Iterate candidates with their positions, starting at `position`
+    fn candidates_from(&self, position: u128) -> Box<dyn Iterator<Item = (u128, Vec<u8>)> + '_> {
+        match self {
+            Generator::Markov(markov) => Box::new(markov.candidates_from(position)),
+            Generator::Prince(prince) => Box::new(prince.candidates_from(position)),
+        }
+    }
 }
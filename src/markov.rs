@@ -0,0 +1,448 @@
+use crate::errors::{CrackerError, Result};
+use crate::wordlist::{expand_wordlist_paths, LineOptions, WordlistReader};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+/// Positions with their own statistics; later positions reuse the last one
+pub const MARKOV_POSITIONS: usize = 32;
+
+/// File magic and format version of a statistics file
+const STATS_MAGIC: &[u8; 8] = b"PBKMARKV";
+const STATS_VERSION: u32 = 1;
+
+/// hcstat-style per-position Markov statistics
+///
+/// `root` counts how often each byte appears at each position; `transitions`
+/// counts how often each byte follows each previous byte at each position.
+/// Stored zstd-compressed, like hashcat's LZMA-compressed `.hcstat2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkovModel {
+    root: Vec<u32>,
+    transitions: Vec<u32>,
+}
+
+impl Default for MarkovModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MarkovModel {
+    pub fn new() -> Self {
+        Self {
+            root: vec![0; MARKOV_POSITIONS * 256],
+            transitions: vec![0; MARKOV_POSITIONS * 256 * 256],
+        }
+    }
+
+    /// Add one training word to the statistics
+    pub fn train(&mut self, word: &[u8]) {
+        for (i, &byte) in word.iter().enumerate() {
+            let position = i.min(MARKOV_POSITIONS - 1);
+            let root = &mut self.root[position * 256 + byte as usize];
+            *root = root.saturating_add(1);
+            if i > 0 {
+                let index = Self::transition_index(position, word[i - 1], byte);
+                self.transitions[index] = self.transitions[index].saturating_add(1);
+            }
+        }
+    }
+
+    /// Train on every word of the given wordlists (files or directories)
+    /// Returns the number of words read.
+    pub fn train_wordlists(&mut self, paths: &[String], options: LineOptions) -> Result<u64> {
+        let mut words = 0;
+        for path in expand_wordlist_paths(paths)? {
+            let mut reader = WordlistReader::new(path)
+                .with_index(false)
+                .with_line_options(options);
+            for entry in reader.read_words()? {
+                self.train(&entry?.word);
+                words += 1;
+            }
+        }
+        Ok(words)
+    }
+
+    /// How often `byte` was seen at `position`
+    pub fn root_count(&self, position: usize, byte: u8) -> u32 {
+        self.root[position.min(MARKOV_POSITIONS - 1) * 256 + byte as usize]
+    }
+
+    /// How often `byte` followed `previous` at `position`
+    pub fn transition_count(&self, position: usize, previous: u8, byte: u8) -> u32 {
+        let position = position.min(MARKOV_POSITIONS - 1);
+        self.transitions[Self::transition_index(position, previous, byte)]
+    }
+
+    fn transition_index(position: usize, previous: u8, byte: u8) -> usize {
+        (position * 256 + previous as usize) * 256 + byte as usize
+    }
+
+    /// Save the statistics to a file
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path)
+            .map_err(|e| CrackerError::MarkovError(format!("Failed to create {}: {}", path, e)))?;
+        let write = || -> std::io::Result<()> {
+            let mut encoder = zstd::Encoder::new(BufWriter::new(file), 9)?;
+            encoder.write_all(STATS_MAGIC)?;
+            encoder.write_all(&STATS_VERSION.to_le_bytes())?;
+            encoder.write_all(&(MARKOV_POSITIONS as u32).to_le_bytes())?;
+            for count in self.root.iter().chain(&self.transitions) {
+                encoder.write_all(&count.to_le_bytes())?;
+            }
+            encoder.finish()?.flush()
+        };
+        write().map_err(|e| CrackerError::MarkovError(format!("Failed to write {}: {}", path, e)))
+    }
+
+    /// Load statistics written by `save`
+    pub fn load(path: &str) -> Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| CrackerError::MarkovError(format!("Failed to open {}: {}", path, e)))?;
+        let mut data = Vec::new();
+        zstd::Decoder::new(BufReader::new(file))
+            .and_then(|mut decoder| decoder.read_to_end(&mut data))
+            .map_err(|e| CrackerError::MarkovError(format!("Failed to read {}: {}", path, e)))?;
+
+        let header = STATS_MAGIC.len() + 8;
+        let mut model = Self::new();
+        let expected = header + 4 * (model.root.len() + model.transitions.len());
+        if data.len() != expected
+            || &data[..STATS_MAGIC.len()] != STATS_MAGIC
+            || data[8..12] != STATS_VERSION.to_le_bytes()
+            || data[12..16] != (MARKOV_POSITIONS as u32).to_le_bytes()
+        {
+            return Err(CrackerError::MarkovError(format!(
+                "{} is not a statistics file written by the train command",
+                path
+            )));
+        }
+
+        let mut counts = data[header..]
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        for count in model.root.iter_mut().chain(model.transitions.iter_mut()) {
+            *count = counts.next().unwrap_or(0);
+        }
+        Ok(model)
+    }
+}
+
+/// Printable ASCII punctuation and space, as in hashcat's `?s`
+const SPECIAL: &[u8] = b" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Parse a hashcat-style mask into one charset per position
+///
+/// `?l` lowercase, `?u` uppercase, `?d` digits, `?s` specials, `?a` all of
+/// these, `?b` every byte, `??` a literal `?`; anything else is a literal.
+pub fn parse_mask(mask: &str) -> Result<Vec<Vec<u8>>> {
+    let mut charsets = Vec::new();
+    let mut bytes = mask.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte != b'?' {
+            charsets.push(vec![byte]);
+            continue;
+        }
+        let charset: Vec<u8> = match bytes.next() {
+            Some(b'l') => (b'a'..=b'z').collect(),
+            Some(b'u') => (b'A'..=b'Z').collect(),
+            Some(b'd') => (b'0'..=b'9').collect(),
+            Some(b's') => SPECIAL.to_vec(),
+            Some(b'a') => (b'a'..=b'z')
+                .chain(b'A'..=b'Z')
+                .chain(b'0'..=b'9')
+                .chain(SPECIAL.iter().copied())
+                .collect(),
+            Some(b'b') => (0..=255).collect(),
+            Some(b'?') => vec![b'?'],
+            Some(other) => {
+                return Err(CrackerError::MarkovError(format!(
+                    "Unknown mask charset ?{}",
+                    other as char
+                )))
+            }
+            None => {
+                return Err(CrackerError::MarkovError(
+                    "Mask ends with a lone '?'".to_string(),
+                ))
+            }
+        };
+        charsets.push(charset);
+    }
+
+    if charsets.is_empty() {
+        return Err(CrackerError::MarkovError("Mask is empty".to_string()));
+    }
+    Ok(charsets)
+}
+
+/// Mask candidates ordered by Markov statistics
+///
+/// Every position's charset is ranked by the model: the first position by
+/// its own counts, later positions by how often each byte followed the
+/// previous one. A threshold keeps only the top N bytes per position.
+/// Candidates are emitted by increasing sum of ranks, so the likeliest
+/// strings (rank 0 everywhere) come first, and any position in that order
+/// can be decoded directly for resuming.
+#[derive(Debug, Clone)]
+pub struct MarkovGenerator {
+    /// `tables[0][0]` ranks the first position; `tables[i][previous]` the others
+    tables: Vec<Vec<Vec<u8>>>,
+    /// Bytes considered at each position
+    radices: Vec<usize>,
+    /// `ways[i][s]`: rank vectors for positions `i..` with rank sum `s`
+    ways: Vec<Vec<u128>>,
+    keyspace: u128,
+}
+
+impl MarkovGenerator {
+    /// `threshold` is the number of bytes kept per position (0 keeps all)
+    pub fn new(model: &MarkovModel, mask: &str, threshold: usize) -> Result<Self> {
+        let charsets = parse_mask(mask)?;
+        let rank = |charset: &[u8], count: &dyn Fn(u8) -> u32| {
+            let mut ranked = charset.to_vec();
+            // Stable sort keeps charset order among equally likely bytes
+            ranked.sort_by_key(|&byte| std::cmp::Reverse(count(byte)));
+            if threshold > 0 {
+                ranked.truncate(threshold);
+            }
+            ranked
+        };
+
+        let mut tables = Vec::with_capacity(charsets.len());
+        tables.push(vec![rank(&charsets[0], &|byte| model.root_count(0, byte))]);
+        for position in 1..charsets.len() {
+            let mut by_previous = vec![Vec::new(); 256];
+            for &previous in &charsets[position - 1] {
+                by_previous[previous as usize] = rank(&charsets[position], &|byte| {
+                    model.transition_count(position, previous, byte)
+                });
+            }
+            tables.push(by_previous);
+        }
+
+        let radices: Vec<usize> = charsets
+            .iter()
+            .map(|charset| {
+                if threshold > 0 {
+                    charset.len().min(threshold)
+                } else {
+                    charset.len()
+                }
+            })
+            .collect();
+
+        let keyspace = radices
+            .iter()
+            .try_fold(1u128, |total, &radix| total.checked_mul(radix as u128))
+            .ok_or_else(|| {
+                CrackerError::MarkovError(format!("Keyspace of mask {} is too large", mask))
+            })?;
+
+        // Count rank vectors per rank sum, from the last position backwards
+        let max_sum: usize = radices.iter().map(|radix| radix - 1).sum();
+        let mut ways = vec![vec![0u128; max_sum + 1]; radices.len() + 1];
+        ways[radices.len()][0] = 1;
+        for i in (0..radices.len()).rev() {
+            for sum in 0..=max_sum {
+                ways[i][sum] = (0..radices[i].min(sum + 1))
+                    .map(|rank| ways[i + 1][sum - rank])
+                    .sum();
+            }
+        }
+
+        Ok(Self {
+            tables,
+            radices,
+            ways,
+            keyspace,
+        })
+    }
+
+    /// Total number of candidates
+    pub fn keyspace(&self) -> u128 {
+        self.keyspace
+    }
+
+    /// Candidate at `position` in probability order
+    pub fn candidate(&self, position: u128) -> Option<Vec<u8>> {
+        self.ranks(position).map(|ranks| self.decode(&ranks))
+    }
+
+    /// Iterate candidates with their positions, starting at `position`
+    pub fn candidates_from(&self, position: u128) -> MarkovCandidates<'_> {
+        MarkovCandidates {
+            generator: self,
+            position,
+            ranks: self.ranks(position),
+        }
+    }
+
+    /// Rank vector at `position`: find its rank sum, then unrank within it
+    fn ranks(&self, mut position: u128) -> Option<Vec<usize>> {
+        if position >= self.keyspace {
+            return None;
+        }
+
+        let mut sum = 0;
+        while position >= self.ways[0][sum] {
+            position -= self.ways[0][sum];
+            sum += 1;
+        }
+
+        let mut ranks = Vec::with_capacity(self.radices.len());
+        for i in 0..self.radices.len() {
+            let mut rank = 0;
+            loop {
+                let count = self.ways[i + 1][sum - rank];
+                if position < count {
+                    break;
+                }
+                position -= count;
+                rank += 1;
+            }
+            ranks.push(rank);
+            sum -= rank;
+        }
+        Some(ranks)
+    }
+
+    /// Next rank vector in order: the next one with the same sum, or the
+    /// first one with the next sum
+    fn advance(&self, ranks: &mut [usize]) -> bool {
+        let last = ranks.len() - 1;
+        let mut suffix = ranks[last];
+        for i in (0..last).rev() {
+            if suffix > 0 && ranks[i] + 1 < self.radices[i] {
+                ranks[i] += 1;
+                self.fill_from_right(&mut ranks[i + 1..], &self.radices[i + 1..], suffix - 1);
+                return true;
+            }
+            suffix += ranks[i];
+        }
+
+        let max_sum: usize = self.radices.iter().map(|radix| radix - 1).sum();
+        if suffix >= max_sum {
+            return false;
+        }
+        self.fill_from_right(ranks, &self.radices, suffix + 1);
+        true
+    }
+
+    /// Smallest rank vector (in order) with the given sum
+    fn fill_from_right(&self, ranks: &mut [usize], radices: &[usize], mut sum: usize) {
+        for (rank, radix) in ranks.iter_mut().zip(radices).rev() {
+            *rank = sum.min(radix - 1);
+            sum -= *rank;
+        }
+    }
+
+    fn decode(&self, ranks: &[usize]) -> Vec<u8> {
+        let mut candidate = Vec::with_capacity(ranks.len());
+        let mut previous = 0;
+        for (i, &rank) in ranks.iter().enumerate() {
+            let table = if i == 0 { 0 } else { previous as usize };
+            let byte = self.tables[i][table][rank];
+            candidate.push(byte);
+            previous = byte;
+        }
+        candidate
+    }
+}
+
+/// Iterator over Markov-ordered candidates and their positions
+pub struct MarkovCandidates<'a> {
+    generator: &'a MarkovGenerator,
+    position: u128,
+    ranks: Option<Vec<usize>>,
+}
+
+impl<'a> Iterator for MarkovCandidates<'a> {
+    type Item = (u128, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ranks = self.ranks.as_mut()?;
+        let item = (self.position, self.generator.decode(ranks));
+        self.position += 1;
+        if !self.generator.advance(ranks) {
+            self.ranks = None;
+        }
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trained(words: &[&str]) -> MarkovModel {
+        let mut model = MarkovModel::new();
+        for word in words {
+            model.train(word.as_bytes());
+        }
+        model
+    }
+
+    #[test]
+    fn test_parse_mask() {
+        let charsets = parse_mask("?d?l??x").unwrap();
+        assert_eq!(charsets.len(), 4);
+        assert_eq!(charsets[0], b"0123456789");
+        assert_eq!(charsets[1].len(), 26);
+        assert_eq!(charsets[2], b"?");
+        assert_eq!(charsets[3], b"x");
+        assert_eq!(parse_mask("?a").unwrap()[0].len(), 95);
+        assert!(parse_mask("?z").is_err());
+        assert!(parse_mask("abc?").is_err());
+        assert!(parse_mask("").is_err());
+    }
+
+    #[test]
+    fn test_candidates_in_probability_order() {
+        let model = trained(&["ab1", "ab1", "ab2", "cd1", "ab1"]);
+        let generator = MarkovGenerator::new(&model, "?l?l?d", 0).unwrap();
+        assert_eq!(generator.keyspace(), 26 * 26 * 10);
+
+        let candidates: Vec<Vec<u8>> = generator.candidates_from(0).map(|(_, c)| c).collect();
+        assert_eq!(candidates.len(), 26 * 26 * 10);
+        assert_eq!(candidates[0], b"ab1");
+        assert_eq!(candidates[1], b"ab2");
+
+        // Every candidate exactly once, and positions decode directly
+        let mut unique = candidates.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), candidates.len());
+        for position in [0, 1, 17, 999, 6759] {
+            assert_eq!(
+                generator.candidate(position).unwrap(),
+                candidates[position as usize]
+            );
+            assert_eq!(
+                generator.candidates_from(position).next().unwrap(),
+                (position, candidates[position as usize].clone())
+            );
+        }
+        assert!(generator.candidate(6760).is_none());
+    }
+
+    #[test]
+    fn test_threshold_and_stats_file() {
+        let model = trained(&["password", "passw0rd", "pa55word"]);
+        let path = std::env::temp_dir()
+            .join(format!(
+                "pbkdf2_cracker_{}_stats.hcstat",
+                std::process::id()
+            ))
+            .to_string_lossy()
+            .into_owned();
+        model.save(&path).unwrap();
+        assert_eq!(MarkovModel::load(&path).unwrap(), model);
+        std::fs::remove_file(&path).unwrap();
+
+        let generator = MarkovGenerator::new(&model, "?a?a?a?a", 2).unwrap();
+        assert_eq!(generator.keyspace(), 16);
+        assert_eq!(generator.candidate(0).unwrap(), b"pass");
+    }
+}
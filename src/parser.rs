@@ -1,4 +1,5 @@
 use crate::errors::{CrackerError, Result};
+use std::fmt;
 
 /// Parsed Flask/Werkzeug PBKDF2-SHA256 hash components
 #[derive(Debug, Clone)]
@@ -82,6 +83,19 @@ impl ParsedHash {
     }
 }
 
+/// Formats the hash in the Flask/Werkzeug format accepted by `ParsedHash::parse`
+impl fmt::Display for ParsedHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pbkdf2:sha256:{}${}${}",
+            self.iterations,
+            String::from_utf8_lossy(&self.salt),
+            hex::encode(&self.digest)
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,305 @@
+use crate::errors::{CrackerError, Result};
+use crate::wordlist::WordlistReader;
+use std::collections::HashSet;
+
+/// Limits on the chains PRINCE builds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrinceOptions {
+    /// Fewest elements chained into one candidate
+    pub min_elements: usize,
+    /// Most elements chained into one candidate
+    pub max_elements: usize,
+    /// Shortest candidate, in bytes
+    pub min_len: usize,
+    /// Longest candidate, in bytes
+    pub max_len: usize,
+}
+
+impl Default for PrinceOptions {
+    fn default() -> Self {
+        Self {
+            min_elements: 1,
+            max_elements: 8,
+            min_len: 1,
+            max_len: 16,
+        }
+    }
+}
+
+/// Element lengths of one chain and the positions it covers
+#[derive(Debug, Clone)]
+struct Chain {
+    lengths: Vec<usize>,
+    /// Position of the chain's first candidate
+    start: u128,
+    keyspace: u128,
+}
+
+/// PRINCE (PRobability INfinite Chained Elements) candidates
+///
+/// Words of one list are grouped by length and chained into candidates.
+/// A chain is a sequence of element lengths, such as 4+2+3; chains are
+/// ordered by total length, then element count, then their lengths, and
+/// every chain yields all combinations of the words of those lengths, in
+/// wordlist order with the last element varying fastest. Any position in
+/// that order can be decoded directly for resuming.
+#[derive(Debug, Clone)]
+pub struct PrinceGenerator {
+    /// `elements[len]`: words of `len` bytes, in wordlist order
+    elements: Vec<Vec<Vec<u8>>>,
+    chains: Vec<Chain>,
+    keyspace: u128,
+}
+
+impl PrinceGenerator {
+    /// Build chains from the given elements
+    ///
+    /// Empty, duplicate and too long elements are dropped.
+    pub fn new(words: impl IntoIterator<Item = Vec<u8>>, options: &PrinceOptions) -> Result<Self> {
+        if options.min_elements == 0 || options.min_elements > options.max_elements {
+            return Err(CrackerError::PrinceError(
+                "Element counts must satisfy 1 <= min <= max".to_string(),
+            ));
+        }
+        if options.min_len > options.max_len {
+            return Err(CrackerError::PrinceError(
+                "Minimum length must not be greater than maximum length".to_string(),
+            ));
+        }
+
+        let mut elements = vec![Vec::new(); options.max_len + 1];
+        let mut seen = HashSet::new();
+        for word in words {
+            if !word.is_empty() && word.len() <= options.max_len && seen.insert(word.clone()) {
+                elements[word.len()].push(word);
+            }
+        }
+
+        let mut chains = Vec::new();
+        let mut keyspace = 0u128;
+        for len in options.min_len.max(1)..=options.max_len {
+            for count in options.min_elements..=options.max_elements.min(len) {
+                let mut lengths = Vec::with_capacity(count);
+                let mut found = Vec::new();
+                compositions(&elements, len, count, &mut lengths, &mut found);
+                for lengths in found {
+                    let chain_keyspace = lengths
+                        .iter()
+                        .try_fold(1u128, |total, &length| {
+                            total.checked_mul(elements[length].len() as u128)
+                        })
+                        .ok_or_else(|| {
+                            CrackerError::PrinceError("Keyspace is too large".to_string())
+                        })?;
+                    chains.push(Chain {
+                        lengths,
+                        start: keyspace,
+                        keyspace: chain_keyspace,
+                    });
+                    keyspace = keyspace.checked_add(chain_keyspace).ok_or_else(|| {
+                        CrackerError::PrinceError("Keyspace is too large".to_string())
+                    })?;
+                }
+            }
+        }
+
+        Ok(Self {
+            elements,
+            chains,
+            keyspace,
+        })
+    }
+
+    /// Build chains from the words of a wordlist
+    pub fn from_wordlist(reader: &mut WordlistReader, options: &PrinceOptions) -> Result<Self> {
+        let mut words = Vec::new();
+        for entry in reader.read_words()? {
+            words.push(entry?.word.into_owned());
+        }
+        Self::new(words, options)
+    }
+
+    /// Number of distinct elements in use
+    pub fn element_count(&self) -> usize {
+        self.elements.iter().map(Vec::len).sum()
+    }
+
+    /// Number of chains in use
+    pub fn chain_count(&self) -> usize {
+        self.chains.len()
+    }
+
+    /// Total number of candidates
+    pub fn keyspace(&self) -> u128 {
+        self.keyspace
+    }
+
+    /// Candidate at `position`
+    pub fn candidate(&self, position: u128) -> Option<Vec<u8>> {
+        self.candidates_from(position).next().map(|(_, word)| word)
+    }
+
+    /// Iterate candidates with their positions, starting at `position`
+    pub fn candidates_from(&self, position: u128) -> PrinceCandidates<'_> {
+        let chain = self
+            .chains
+            .partition_point(|chain| chain.start + chain.keyspace <= position);
+        let digits = self.chains.get(chain).map(|c| {
+            // Mixed-radix digits of the offset, last element fastest
+            let mut offset = position - c.start;
+            let mut digits = vec![0; c.lengths.len()];
+            for (digit, &length) in digits.iter_mut().zip(&c.lengths).rev() {
+                let radix = self.elements[length].len() as u128;
+                *digit = (offset % radix) as usize;
+                offset /= radix;
+            }
+            digits
+        });
+
+        PrinceCandidates {
+            generator: self,
+            position,
+            chain,
+            digits: digits.unwrap_or_default(),
+        }
+    }
+}
+
+/// Collect every way to split `len` bytes into `count` lengths that have elements
+fn compositions(
+    elements: &[Vec<Vec<u8>>],
+    len: usize,
+    count: usize,
+    lengths: &mut Vec<usize>,
+    found: &mut Vec<Vec<usize>>,
+) {
+    if count == 0 {
+        if len == 0 {
+            found.push(lengths.clone());
+        }
+        return;
+    }
+    // Leave at least one byte for each remaining element
+    for length in 1..=len - (count - 1) {
+        if elements[length].is_empty() {
+            continue;
+        }
+        lengths.push(length);
+        compositions(elements, len - length, count - 1, lengths, found);
+        lengths.pop();
+    }
+}
+
+pub struct PrinceCandidates<'a> {
+    generator: &'a PrinceGenerator,
+    position: u128,
+    chain: usize,
+    digits: Vec<usize>,
+}
+
+impl<'a> Iterator for PrinceCandidates<'a> {
+    type Item = (u128, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chain = self.generator.chains.get(self.chain)?;
+        let elements = &self.generator.elements;
+
+        let mut word = Vec::new();
+        for (&digit, &length) in self.digits.iter().zip(&chain.lengths) {
+            word.extend_from_slice(&elements[length][digit]);
+        }
+        let item = (self.position, word);
+        self.position += 1;
+
+        // Advance like an odometer; on wrap-around, move to the next chain
+        let mut wrapped = true;
+        for (digit, &length) in self.digits.iter_mut().zip(&chain.lengths).rev() {
+            *digit += 1;
+            if *digit < elements[length].len() {
+                wrapped = false;
+                break;
+            }
+            *digit = 0;
+        }
+        if wrapped {
+            self.chain += 1;
+            if let Some(next) = self.generator.chains.get(self.chain) {
+                self.digits = vec![0; next.lengths.len()];
+            }
+        }
+
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(words: &[&str], options: &PrinceOptions) -> PrinceGenerator {
+        let words = words.iter().map(|word| word.as_bytes().to_vec());
+        PrinceGenerator::new(words, options).unwrap()
+    }
+
+    fn texts(generator: &PrinceGenerator, position: u128) -> Vec<String> {
+        generator
+            .candidates_from(position)
+            .map(|(_, word)| String::from_utf8(word).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_chains_ordered_by_length() {
+        let options = PrinceOptions {
+            min_elements: 1,
+            max_elements: 2,
+            min_len: 1,
+            max_len: 4,
+        };
+        let generator = generator(&["ab", "c", "ab", "d", "toolong"], &options);
+        assert_eq!(generator.element_count(), 3);
+        assert_eq!(
+            texts(&generator, 0),
+            vec!["c", "d", "ab", "cc", "cd", "dc", "dd", "cab", "dab", "abc", "abd", "abab"]
+        );
+        assert_eq!(generator.keyspace(), 12);
+
+        let options = PrinceOptions {
+            min_elements: 2,
+            max_elements: 2,
+            min_len: 3,
+            max_len: 3,
+        };
+        let generator = self::generator(&["ab", "c"], &options);
+        assert_eq!(texts(&generator, 0), vec!["cab", "abc"]);
+
+        let options = PrinceOptions {
+            min_elements: 3,
+            max_elements: 2,
+            ..PrinceOptions::default()
+        };
+        assert!(PrinceGenerator::new(Vec::new(), &options).is_err());
+    }
+
+    #[test]
+    fn test_positions_resume() {
+        let options = PrinceOptions {
+            min_elements: 1,
+            max_elements: 3,
+            min_len: 2,
+            max_len: 7,
+        };
+        let generator = generator(&["a", "bb", "cc", "ddd", "e"], &options);
+        let all: Vec<(u128, Vec<u8>)> = generator.candidates_from(0).collect();
+        assert_eq!(all.len() as u128, generator.keyspace());
+        for (expected, (position, word)) in all.iter().enumerate() {
+            assert_eq!(*position, expected as u128);
+            assert_eq!(generator.candidate(*position).as_ref(), Some(word));
+            assert_eq!(
+                generator.candidates_from(*position).next().as_ref(),
+                Some(&all[expected])
+            );
+        }
+        assert_eq!(generator.candidate(generator.keyspace()), None);
+    }
+}
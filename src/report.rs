@@ -0,0 +1,342 @@
+use serde::Serialize;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// What a run writes to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Banner, headers and statistics for people
+    #[default]
+    Human,
+    /// Only `hash:password` lines for cracked hashes
+    Quiet,
+    /// Newline-delimited `StatusEvent`s only
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Output {
+    mode: OutputMode,
+    plain: bool,
+}
+
+static OUTPUT: OnceLock<Output> = OnceLock::new();
+
+/// Choose the output mode and whether human output is plain ASCII; only the
+/// first call has an effect
+pub fn configure(mode: OutputMode, plain: bool) {
+    let _ = OUTPUT.set(Output { mode, plain });
+}
+
+fn output() -> Output {
+    OUTPUT.get().copied().unwrap_or_default()
+}
+
+pub fn mode() -> OutputMode {
+    output().mode
+}
+
+pub fn is_human() -> bool {
+    mode() == OutputMode::Human
+}
+
+/// Whether emoji, colors and box drawing are left out
+pub fn is_plain() -> bool {
+    output().plain
+}
+
+/// Print a line of human output to stdout; used by `say!`
+pub fn say(args: fmt::Arguments) {
+    if is_human() {
+        println!("{}", styled(args));
+    }
+}
+
+/// Print a warning or error to stderr in every mode; used by `say_err!`
+pub fn say_err(args: fmt::Arguments) {
+    eprintln!("{}", styled(args));
+}
+
+fn styled(args: fmt::Arguments) -> String {
+    let text = args.to_string();
+    if is_plain() {
+        plain_text(&text)
+    } else {
+        text
+    }
+}
+
+/// `println!` for human output: silent in the quiet and JSON modes, and
+/// without decorations in the plain mode
+#[macro_export]
+macro_rules! say {
+    () => {
+        $crate::report::say(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::report::say(format_args!($($arg)*))
+    };
+}
+
+/// `eprintln!` for warnings and errors, without decorations in the plain mode
+#[macro_export]
+macro_rules! say_err {
+    ($($arg:tt)*) => {
+        $crate::report::say_err(format_args!($($arg)*))
+    };
+}
+
+/// Program banner
+pub fn banner() {
+    if is_plain() {
+        say(format_args!(
+            "\nPBKDF2-SHA256 Password Cracker v1.0.0\nFlask/Werkzeug Hash Cracker by Mikro\n"
+        ));
+        return;
+    }
+    say(format_args!(""));
+    say(format_args!(
+        "╔══════════════════════════════════════════════════╗"
+    ));
+    say(format_args!(
+        "║                                                  ║"
+    ));
+    say(format_args!(
+        "║    PBKDF2-SHA256 Password Cracker v1.0.0         ║"
+    ));
+    say(format_args!(
+        "║    Flask/Werkzeug Hash Cracker by Mikro          ║"
+    ));
+    say(format_args!(
+        "║                                                  ║"
+    ));
+    say(format_args!(
+        "║                                                  ║"
+    ));
+    say(format_args!(
+        "╚══════════════════════════════════════════════════╝"
+    ));
+    say(format_args!(""));
+}
+
+/// A result headline framed by `icon`, such as "🔥 PASSWORD FOUND 🔥"
+pub fn headline(icon: &str, text: &str) {
+    if is_plain() {
+        say(format_args!("{}", text));
+    } else {
+        say(format_args!("{} {} {}", icon, text, icon));
+    }
+}
+
+/// Print a bare result line, in the quiet mode only
+pub fn result(args: fmt::Arguments) {
+    if mode() == OutputMode::Quiet {
+        println!("{}", args);
+    }
+}
+
+/// Print a cracked hash as `hash:password`, in the quiet mode only
+pub fn cracked(hash: &str, password: &str) {
+    result(format_args!("{}:{}", hash, password));
+}
+
+/// Remove emoji and box drawing from human output
+///
+/// Icons only start lines and box drawing only forms whole rule lines, so
+/// the rest of a line, which may hold a password, is kept as is.
+pub fn plain_text(text: &str) -> String {
+    text.split('\n')
+        .map(plain_line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn plain_line(line: &str) -> String {
+    let body = line.trim_start();
+    let indent = &line[..line.len() - body.len()];
+    if !body.is_empty() && body.chars().all(|c| is_box(c) || c.is_whitespace()) {
+        return line
+            .chars()
+            .map(|c| match c {
+                '║' | '│' | '┃' => '|',
+                c if is_box(c) && c != '═' && c != '━' && c != '─' => '+',
+                c if is_box(c) => '-',
+                c => c,
+            })
+            .collect();
+    }
+    match body.strip_prefix(is_icon) {
+        Some(_) => format!(
+            "{}{}",
+            indent,
+            body.trim_start_matches(|c: char| is_icon(c) || c.is_whitespace())
+        ),
+        None => line.to_string(),
+    }
+}
+
+fn is_box(c: char) -> bool {
+    ('\u{2500}'..='\u{257F}').contains(&c)
+}
+
+fn is_icon(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x2190..=0x21FF | 0x2300..=0x23FF | 0x2600..=0x27BF | 0x1F000..=0x1FAFF | 0xFE0F | 0x200D
+    )
+}
+
+/// One line of the `--status-json` stream
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StatusEvent {
+    /// The session's configuration, before any candidate is tested
+    Start {
+        timestamp: String,
+        hashes: Vec<String>,
+        sources: Vec<String>,
+        attack_mode: String,
+        rules: usize,
+        threads: usize,
+        /// (word, rule) pairs left to test
+        keyspace: u64,
+        checkpoint: String,
+        /// Where a resumed session continues, if it was resumed
+        resumed_from: Option<ResumePoint>,
+    },
+    /// Periodic progress
+    Progress {
+        /// Hash derivations, including earlier runs of a resumed session
+        attempts: u64,
+        /// Hash derivations per second in this run
+        rate: f64,
+        /// (word, rule) pairs of `keyspace` done
+        position: u64,
+        keyspace: u64,
+        eta_secs: Option<f64>,
+        elapsed_secs: f64,
+        word: String,
+    },
+    /// A hash matched
+    Cracked {
+        /// Position of the hash among the session's hashes
+        index: usize,
+        label: String,
+        hash: String,
+        password: String,
+        base_word: String,
+        rule: String,
+    },
+    /// A checkpoint was written
+    Checkpoint {
+        path: String,
+        source: String,
+        line: u64,
+        rule_index: usize,
+        attempts: u64,
+    },
+    /// The session ended
+    ///
+    /// A session that failed with an error only reports the error.
+    Finish {
+        /// `all_cracked`, `exhausted`, `interrupted` or `error`
+        reason: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cracked: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hashes: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        attempts: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        elapsed_secs: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+/// Source, line and rule a resumed session starts at
+#[derive(Debug, Clone, Serialize)]
+pub struct ResumePoint {
+    pub source: String,
+    pub line: u64,
+    pub rule_index: usize,
+}
+
+/// Write an event as one JSON line in the JSON mode; in the quiet mode only
+/// cracks are written, as `hash:password`
+pub fn emit(event: &StatusEvent) {
+    match mode() {
+        OutputMode::Json => {
+            if let Ok(line) = serde_json::to_string(event) {
+                println!("{}", line);
+            }
+        }
+        OutputMode::Quiet => {
+            if let StatusEvent::Cracked { hash, password, .. } = event {
+                cracked(hash, password);
+            }
+        }
+        OutputMode::Human => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_tagged_lines() {
+        let event = StatusEvent::Finish {
+            reason: "exhausted".to_string(),
+            cracked: Some(0),
+            hashes: Some(2),
+            attempts: Some(10),
+            elapsed_secs: Some(1.5),
+            error: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"finish","reason":"exhausted","cracked":0,"hashes":2,"attempts":10,"elapsed_secs":1.5}"#
+        );
+
+        let event = StatusEvent::Checkpoint {
+            path: "c.json".to_string(),
+            source: "words.txt".to_string(),
+            line: 3,
+            rule_index: 1,
+            attempts: 7,
+        };
+        let line = serde_json::to_string(&event).unwrap();
+        assert!(line.starts_with(r#"{"event":"checkpoint","path":"c.json""#));
+        assert!(!line.contains('\n'));
+
+        let event = StatusEvent::Finish {
+            reason: "error".to_string(),
+            cracked: None,
+            hashes: None,
+            attempts: None,
+            elapsed_secs: None,
+            error: Some("Wordlist not found: x".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"finish","reason":"error","error":"Wordlist not found: x"}"#
+        );
+    }
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(plain_text("🔍 Parsing hash..."), "Parsing hash...");
+        assert_eq!(
+            plain_text("⏱  Total Time:     2m 03s"),
+            "Total Time:     2m 03s"
+        );
+        assert_eq!(
+            plain_text("\n😞 Password not found\n   🔓 alice: p🔥ss"),
+            "\nPassword not found\n   alice: p🔥ss"
+        );
+        assert_eq!(plain_text("━━━━"), "----");
+        assert_eq!(plain_text("╔══╗"), "+--+");
+        assert_eq!(plain_text("   Password: ünïcode"), "   Password: ünïcode");
+    }
+}
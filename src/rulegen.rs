@@ -0,0 +1,130 @@
+use crate::rules::{Rule, LEET_TABLE};
+use std::collections::HashSet;
+
+/// Longest chain of primitives in a generated rule
+const MAX_CHAIN_LEN: usize = 3;
+
+/// Characters generated rules may insert, chosen to stay valid in rules files
+const INSERT_CHARS: &[char] = &[
+    '!', '@', '#', '$', '%', '&', '*', '.', '_', '-', '?', '1', '0',
+];
+
+/// Random rule generator (hashcat `-g` style)
+///
+/// The same seed always produces the same rules, so a run with a
+/// generated rule set can be repeated or resumed exactly.
+pub struct RuleGenerator {
+    rng: SplitMix64,
+}
+
+impl RuleGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    /// Generate `count` distinct random rules of one to `MAX_CHAIN_LEN` primitives
+    pub fn generate(&mut self, count: usize) -> Vec<Rule> {
+        let mut seen = HashSet::new();
+        let mut rules = Vec::with_capacity(count);
+        // Bound the attempts so tiny primitive spaces cannot loop forever
+        let mut attempts = 0;
+
+        while rules.len() < count && attempts < count.saturating_mul(20).max(100) {
+            attempts += 1;
+            let len = 1 + self.rng.below(MAX_CHAIN_LEN as u64) as usize;
+            let rule = if len == 1 {
+                self.primitive()
+            } else {
+                Rule::Chain((0..len).map(|_| self.primitive()).collect())
+            };
+
+            if seen.insert(rule.to_string()) {
+                rules.push(rule);
+            }
+        }
+
+        rules
+    }
+
+    /// Pick a random single-candidate primitive with random parameters
+    fn primitive(&mut self) -> Rule {
+        match self.rng.below(20) {
+            0 => Rule::AppendDigit(self.rng.below(10) as u32),
+            1 => Rule::PrependDigit(self.rng.below(10) as u32),
+            2 => Rule::UppercaseFirst,
+            3 => Rule::Lowercase,
+            4 => Rule::Uppercase,
+            5 => Rule::Reverse,
+            6 => Rule::AppendSpecial(self.insert_char()),
+            7 => Rule::PrependSpecial(self.insert_char()),
+            8 => Rule::Duplicate,
+            9 => Rule::AppendYear(1990 + self.rng.below(41) as u32),
+            10 => {
+                let (from, to) = LEET_TABLE[self.rng.below(LEET_TABLE.len() as u64) as usize];
+                Rule::Substitute(from, to)
+            }
+            11 => Rule::TogglePosition(self.position()),
+            12 => Rule::InsertAt(self.position(), self.insert_char()),
+            13 => Rule::DeleteAt(self.position()),
+            14 => Rule::OverwriteAt(self.position(), self.insert_char()),
+            15 => Rule::Truncate(4 + self.rng.below(9) as usize),
+            16 => Rule::DeleteFirst,
+            17 => Rule::DeleteLast,
+            18 => Rule::RotateLeft,
+            _ => Rule::RotateRight,
+        }
+    }
+
+    fn position(&mut self) -> usize {
+        self.rng.below(8) as usize
+    }
+
+    fn insert_char(&mut self) -> char {
+        INSERT_CHARS[self.rng.below(INSERT_CHARS.len() as u64) as usize]
+    }
+}
+
+/// SplitMix64 PRNG: tiny, fast and stable across releases, unlike
+/// `std`'s randomly keyed hashers
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform-enough value in `0..bound` for small bounds
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_rules_are_reproducible_and_parseable() {
+        let first = RuleGenerator::new(42).generate(200);
+        let second = RuleGenerator::new(42).generate(200);
+        assert_eq!(first.len(), 200);
+        assert_eq!(first, second);
+        assert_ne!(first, RuleGenerator::new(43).generate(200));
+
+        for rule in &first {
+            assert_eq!(Rule::parse(&rule.to_string()).as_ref(), Some(rule));
+        }
+    }
+}
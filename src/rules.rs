@@ -1,9 +1,13 @@
+use crate::encoding::{to_bytes, to_text};
 use crate::errors::{CrackerError, Result};
+use crate::filter::CharClass;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Rule engine for password mutations (similar to hashcat rules)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Rule {
     None,                      // No mutation
     AppendDigit(u32),          // Append digit (0-999)
@@ -16,11 +20,63 @@ pub enum Rule {
     PrependSpecial(char),      // Prepend special character
     Duplicate,                 // Duplicate the password
     AppendYear(u32),           // Append year (2000-2030)
+    Substitute(char, char),    // Replace every occurrence of X with Y
+    Leet,                      // Apply the full leetspeak table
+    LeetCombinations(usize),   // Every leetspeak subset (max substitutable positions)
+    TogglePosition(usize),     // Toggle case of character at position N
+    TogglePermutations(usize), // Every case permutation (max word length)
+    InsertAt(usize, char),     // Insert character at position N
+    DeleteAt(usize),           // Delete character at position N
+    OverwriteAt(usize, char),  // Overwrite character at position N
+    Truncate(usize),           // Keep only the first N characters
+    DeleteFirst,               // Delete the first character
+    DeleteLast,                // Delete the last character
+    RotateLeft,                // Move the first character to the end
+    RotateRight,               // Move the last character to the front
+    Extract(usize, usize),     // Keep M characters starting at position N
+    RejectShorter(usize),      // Reject if shorter than N characters
+    RejectLonger(usize),       // Reject if longer than N characters
+    RejectUnless(CharClass),   // Reject unless it contains a character of the class
+    RejectContains(char),      // Reject if it contains the character
+    Chain(Vec<Rule>),          // Apply several rules in sequence
+}
+
+/// Leetspeak substitution table used by `Rule::Leet` and `Rule::LeetCombinations`
+pub const LEET_TABLE: &[(char, char)] = &[
+    ('a', '@'),
+    ('e', '3'),
+    ('i', '1'),
+    ('o', '0'),
+    ('s', '$'),
+    ('t', '7'),
+];
+
+/// Look up the leetspeak replacement for a character (case-insensitive)
+fn leet_char(c: char) -> Option<char> {
+    let lower = c.to_ascii_lowercase();
+    LEET_TABLE
+        .iter()
+        .find(|(from, _)| *from == lower)
+        .map(|(_, to)| *to)
+}
+
+/// Swap the case of a single character
+fn toggle_char(c: char, out: &mut String) {
+    if c.is_lowercase() {
+        out.extend(c.to_uppercase());
+    } else if c.is_uppercase() {
+        out.extend(c.to_lowercase());
+    } else {
+        out.push(c);
+    }
 }
 
 impl Rule {
-    /// Apply rule to a password
-    pub fn apply(&self, password: &str) -> String {
+    /// Apply a rule's single transformation to a password
+    ///
+    /// Rejection and multi-output rules need `expand`, the only public way
+    /// to apply a rule; chains are expanded rule by rule there too.
+    fn apply(&self, password: &str) -> String {
         match self {
             Rule::None => password.to_string(),
             Rule::AppendDigit(n) => format!("{}{}", password, n),
@@ -39,6 +95,134 @@ impl Rule {
             Rule::PrependSpecial(c) => format!("{}{}", c, password),
             Rule::Duplicate => format!("{}{}", password, password),
             Rule::AppendYear(y) => format!("{}{}", password, y),
+            Rule::Substitute(from, to) => password
+                .chars()
+                .map(|c| if c == *from { *to } else { c })
+                .collect(),
+            Rule::Leet | Rule::LeetCombinations(_) => password
+                .chars()
+                .map(|c| leet_char(c).unwrap_or(c))
+                .collect(),
+            Rule::TogglePosition(n) => {
+                let mut out = String::with_capacity(password.len());
+                for (i, c) in password.chars().enumerate() {
+                    if i == *n {
+                        toggle_char(c, &mut out);
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+            Rule::TogglePermutations(_) => {
+                let mut out = String::with_capacity(password.len());
+                for c in password.chars() {
+                    toggle_char(c, &mut out);
+                }
+                out
+            }
+            // Positional rules work on characters, not bytes, and leave the
+            // password unchanged when the position is out of range
+            Rule::InsertAt(n, c) => {
+                let mut chars: Vec<char> = password.chars().collect();
+                if *n <= chars.len() {
+                    chars.insert(*n, *c);
+                }
+                chars.into_iter().collect()
+            }
+            Rule::DeleteAt(n) => {
+                let mut chars: Vec<char> = password.chars().collect();
+                if *n < chars.len() {
+                    chars.remove(*n);
+                }
+                chars.into_iter().collect()
+            }
+            Rule::OverwriteAt(n, c) => {
+                let mut chars: Vec<char> = password.chars().collect();
+                if let Some(slot) = chars.get_mut(*n) {
+                    *slot = *c;
+                }
+                chars.into_iter().collect()
+            }
+            Rule::Truncate(n) => password.chars().take(*n).collect(),
+            Rule::DeleteFirst => password.chars().skip(1).collect(),
+            Rule::DeleteLast => {
+                let mut chars: Vec<char> = password.chars().collect();
+                chars.pop();
+                chars.into_iter().collect()
+            }
+            Rule::RotateLeft => {
+                let mut chars: Vec<char> = password.chars().collect();
+                if !chars.is_empty() {
+                    chars.rotate_left(1);
+                }
+                chars.into_iter().collect()
+            }
+            Rule::RotateRight => {
+                let mut chars: Vec<char> = password.chars().collect();
+                if !chars.is_empty() {
+                    chars.rotate_right(1);
+                }
+                chars.into_iter().collect()
+            }
+            Rule::Extract(start, len) => {
+                if *start < password.chars().count() {
+                    password.chars().skip(*start).take(*len).collect()
+                } else {
+                    password.to_string()
+                }
+            }
+            // Rejection rules never modify the password, see `rejects`
+            Rule::RejectShorter(_)
+            | Rule::RejectLonger(_)
+            | Rule::RejectUnless(_)
+            | Rule::RejectContains(_) => password.to_string(),
+            Rule::Chain(_) => unreachable!("chains are applied by expand"),
+        }
+    }
+
+    /// Check whether a rejection rule discards the password
+    pub fn rejects(&self, password: &str) -> bool {
+        match self {
+            Rule::RejectShorter(n) => password.chars().count() < *n,
+            Rule::RejectLonger(n) => password.chars().count() > *n,
+            Rule::RejectUnless(class) => !password.chars().any(|c| class.matches(c)),
+            Rule::RejectContains(c) => password.contains(*c),
+            _ => false,
+        }
+    }
+
+    /// Apply rule to a password and return every candidate it produces
+    ///
+    /// Most rules produce exactly one candidate. `LeetCombinations` and
+    /// `TogglePermutations` produce one candidate per non-empty subset of
+    /// substitutable positions, and nothing at all for words beyond their cap.
+    pub fn expand(&self, password: &str) -> Vec<String> {
+        match self {
+            Rule::LeetCombinations(max) => {
+                let chars: Vec<char> = password.chars().collect();
+                let positions: Vec<usize> = (0..chars.len())
+                    .filter(|&i| leet_char(chars[i]).is_some())
+                    .collect();
+                subset_variants(&chars, &positions, *max, |c, out| {
+                    out.push(leet_char(c).unwrap_or(c))
+                })
+            }
+            Rule::TogglePermutations(max) => {
+                let chars: Vec<char> = password.chars().collect();
+                if chars.len() > *max {
+                    return Vec::new();
+                }
+                let positions: Vec<usize> = (0..chars.len())
+                    .filter(|&i| chars[i].is_lowercase() || chars[i].is_uppercase())
+                    .collect();
+                subset_variants(&chars, &positions, *max, toggle_char)
+            }
+            Rule::Chain(rules) => rules.iter().fold(vec![password.to_string()], |current, rule| {
+                current.iter().flat_map(|pw| rule.expand(pw)).collect()
+            }),
+            _ if self.rejects(password) => Vec::new(),
+            _ => vec![self.apply(password)],
         }
     }
 
@@ -50,13 +234,43 @@ impl Rule {
     /// - "lowercase"
     /// - "reverse"
     /// - "append_special:!"
+    /// - "substitute:a:@"
+    /// - "leet"
+    /// - "leet_combinations:8"
+    /// - "toggle:0"
+    /// - "toggle_permutations:8"
+    /// - "insert:0:!"
+    /// - "delete:2"
+    /// - "overwrite:0:P"
+    /// - "truncate:8"
+    /// - "extract:0:4"
+    /// - "reject_shorter:8"
+    /// - "reject_unless:digit"
+    /// - "reject_contains:@"
+    ///
+    /// Several rules separated by whitespace form a chain that is applied
+    /// left to right, e.g. "lowercase append_digit:1 reject_shorter:8".
+    ///
+    /// Character arguments escape `:` as `\:`, `\` as `\\`, a space as `\s`
+    /// and other whitespace as `\u{...}`, e.g. "substitute:\s:_".
     pub fn parse(line: &str) -> Option<Rule> {
         let line = line.trim();
         if line.is_empty() || line.starts_with('#') {
             return None;
         }
 
-        let parts: Vec<&str> = line.split(':').collect();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() > 1 {
+            let rules: Option<Vec<Rule>> = tokens.iter().map(|t| Rule::parse_single(t)).collect();
+            return rules.map(Rule::Chain);
+        }
+
+        Rule::parse_single(line)
+    }
+
+    /// Parse a single rule token
+    fn parse_single(token: &str) -> Option<Rule> {
+        let parts = split_fields(token);
 
         match parts[0] {
             "none" => Some(Rule::None),
@@ -79,15 +293,15 @@ impl Rule {
             "uppercase" => Some(Rule::Uppercase),
             "reverse" => Some(Rule::Reverse),
             "append_special" => {
-                if parts.len() == 2 && parts[1].len() == 1 {
-                    parts[1].chars().next().map(Rule::AppendSpecial)
+                if parts.len() == 2 {
+                    single_char(parts[1]).map(Rule::AppendSpecial)
                 } else {
                     None
                 }
             }
             "prepend_special" => {
-                if parts.len() == 2 && parts[1].len() == 1 {
-                    parts[1].chars().next().map(Rule::PrependSpecial)
+                if parts.len() == 2 {
+                    single_char(parts[1]).map(Rule::PrependSpecial)
                 } else {
                     None
                 }
@@ -100,11 +314,254 @@ impl Rule {
                     None
                 }
             }
+            "substitute" => {
+                if parts.len() == 3 {
+                    match (single_char(parts[1]), single_char(parts[2])) {
+                        (Some(from), Some(to)) => Some(Rule::Substitute(from, to)),
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            }
+            "leet" => Some(Rule::Leet),
+            "leet_combinations" => {
+                if parts.len() == 2 {
+                    parts[1].parse::<usize>().ok().map(Rule::LeetCombinations)
+                } else {
+                    None
+                }
+            }
+            "toggle" => {
+                if parts.len() == 2 {
+                    parts[1].parse::<usize>().ok().map(Rule::TogglePosition)
+                } else {
+                    None
+                }
+            }
+            "toggle_permutations" => {
+                if parts.len() == 2 {
+                    parts[1].parse::<usize>().ok().map(Rule::TogglePermutations)
+                } else {
+                    None
+                }
+            }
+            "insert" => {
+                if parts.len() == 3 {
+                    match (parts[1].parse::<usize>().ok(), single_char(parts[2])) {
+                        (Some(n), Some(c)) => Some(Rule::InsertAt(n, c)),
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            }
+            "delete" => {
+                if parts.len() == 2 {
+                    parts[1].parse::<usize>().ok().map(Rule::DeleteAt)
+                } else {
+                    None
+                }
+            }
+            "overwrite" => {
+                if parts.len() == 3 {
+                    match (parts[1].parse::<usize>().ok(), single_char(parts[2])) {
+                        (Some(n), Some(c)) => Some(Rule::OverwriteAt(n, c)),
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            }
+            "truncate" => {
+                if parts.len() == 2 {
+                    parts[1].parse::<usize>().ok().map(Rule::Truncate)
+                } else {
+                    None
+                }
+            }
+            "delete_first" => Some(Rule::DeleteFirst),
+            "delete_last" => Some(Rule::DeleteLast),
+            "rotate_left" => Some(Rule::RotateLeft),
+            "rotate_right" => Some(Rule::RotateRight),
+            "extract" => {
+                if parts.len() == 3 {
+                    match (parts[1].parse::<usize>().ok(), parts[2].parse::<usize>().ok()) {
+                        (Some(start), Some(len)) => Some(Rule::Extract(start, len)),
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            }
+            "reject_shorter" => {
+                if parts.len() == 2 {
+                    parts[1].parse::<usize>().ok().map(Rule::RejectShorter)
+                } else {
+                    None
+                }
+            }
+            "reject_longer" => {
+                if parts.len() == 2 {
+                    parts[1].parse::<usize>().ok().map(Rule::RejectLonger)
+                } else {
+                    None
+                }
+            }
+            "reject_unless" => {
+                if parts.len() == 2 {
+                    CharClass::parse(parts[1]).map(Rule::RejectUnless)
+                } else {
+                    None
+                }
+            }
+            "reject_contains" => {
+                if parts.len() == 2 {
+                    single_char(parts[1]).map(Rule::RejectContains)
+                } else {
+                    None
+                }
+            }
             _ => None,
         }
     }
 }
 
+/// Formats a rule in the same syntax accepted by `Rule::parse`
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::None => write!(f, "none"),
+            Rule::AppendDigit(n) => write!(f, "append_digit:{}", n),
+            Rule::PrependDigit(n) => write!(f, "prepend_digit:{}", n),
+            Rule::UppercaseFirst => write!(f, "uppercase_first"),
+            Rule::Lowercase => write!(f, "lowercase"),
+            Rule::Uppercase => write!(f, "uppercase"),
+            Rule::Reverse => write!(f, "reverse"),
+            Rule::AppendSpecial(c) => write!(f, "append_special:{}", Arg(*c)),
+            Rule::PrependSpecial(c) => write!(f, "prepend_special:{}", Arg(*c)),
+            Rule::Duplicate => write!(f, "duplicate"),
+            Rule::AppendYear(y) => write!(f, "append_year:{}", y),
+            Rule::Substitute(from, to) => {
+                write!(f, "substitute:{}:{}", Arg(*from), Arg(*to))
+            }
+            Rule::Leet => write!(f, "leet"),
+            Rule::LeetCombinations(n) => write!(f, "leet_combinations:{}", n),
+            Rule::TogglePosition(n) => write!(f, "toggle:{}", n),
+            Rule::TogglePermutations(n) => write!(f, "toggle_permutations:{}", n),
+            Rule::InsertAt(n, c) => write!(f, "insert:{}:{}", n, Arg(*c)),
+            Rule::DeleteAt(n) => write!(f, "delete:{}", n),
+            Rule::OverwriteAt(n, c) => write!(f, "overwrite:{}:{}", n, Arg(*c)),
+            Rule::Truncate(n) => write!(f, "truncate:{}", n),
+            Rule::DeleteFirst => write!(f, "delete_first"),
+            Rule::DeleteLast => write!(f, "delete_last"),
+            Rule::RotateLeft => write!(f, "rotate_left"),
+            Rule::RotateRight => write!(f, "rotate_right"),
+            Rule::Extract(start, len) => write!(f, "extract:{}:{}", start, len),
+            Rule::RejectShorter(n) => write!(f, "reject_shorter:{}", n),
+            Rule::RejectLonger(n) => write!(f, "reject_longer:{}", n),
+            Rule::RejectUnless(class) => write!(f, "reject_unless:{}", class),
+            Rule::RejectContains(c) => write!(f, "reject_contains:{}", Arg(*c)),
+            Rule::Chain(rules) => {
+                for (i, rule) in rules.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", rule)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parse a rule argument that must be exactly one character
+fn single_char(s: &str) -> Option<char> {
+    let c = match s {
+        "\\:" => ':',
+        "\\\\" => '\\',
+        "\\s" => ' ',
+        _ => match s.strip_prefix("\\u{").and_then(|hex| hex.strip_suffix('}')) {
+            Some(hex) => char::from_u32(u32::from_str_radix(hex, 16).ok()?)?,
+            None => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return None,
+                }
+            }
+        },
+    };
+    Some(c)
+}
+
+/// Split a rule token on `:`, except where the `:` is escaped as `\:`
+fn split_fields(token: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in token.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ':' => {
+                fields.push(&token[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&token[start..]);
+    fields
+}
+
+/// A character argument as `Rule::parse` reads it back, see `single_char`
+struct Arg(char);
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            ':' => write!(f, "\\:"),
+            '\\' => write!(f, "\\\\"),
+            ' ' => write!(f, "\\s"),
+            c if c.is_whitespace() => write!(f, "\\u{{{:x}}}", c as u32),
+            c => write!(f, "{}", c),
+        }
+    }
+}
+
+/// Produce one variant per non-empty subset of `positions`, rewriting the
+/// selected characters with `transform`. Returns nothing if there are more
+/// than `max` positions, since the output doubles with every position.
+fn subset_variants<F>(chars: &[char], positions: &[usize], max: usize, transform: F) -> Vec<String>
+where
+    F: Fn(char, &mut String),
+{
+    if positions.is_empty() || positions.len() > max || positions.len() >= usize::BITS as usize {
+        return Vec::new();
+    }
+
+    let mut variants = Vec::with_capacity((1 << positions.len()) - 1);
+    for mask in 1usize..(1 << positions.len()) {
+        let mut out = String::with_capacity(chars.len());
+        let mut next = 0;
+        for (i, &c) in chars.iter().enumerate() {
+            if next < positions.len() && positions[next] == i {
+                if mask & (1 << next) != 0 {
+                    transform(c, &mut out);
+                } else {
+                    out.push(c);
+                }
+                next += 1;
+            } else {
+                out.push(c);
+            }
+        }
+        variants.push(out);
+    }
+    variants
+}
+
 /// Rule engine that manages all rules
 pub struct RuleEngine {
     rules: Vec<Rule>,
@@ -171,19 +628,129 @@ impl RuleEngine {
         Self { rules }
     }
 
+    /// Create a rule engine with leetspeak, substitution and toggle-case mutations
+    pub fn leet_rules() -> Self {
+        let mut rules = vec![Rule::None, Rule::Leet, Rule::LeetCombinations(8)];
+
+        // Add alternative substitutions not covered by the leet table
+        for (from, to) in [
+            ('a', '4'),
+            ('i', '!'),
+            ('l', '1'),
+            ('s', '5'),
+            ('g', '9'),
+            ('b', '8'),
+        ] {
+            rules.push(Rule::Substitute(from, to));
+        }
+
+        // Add case toggles for the first characters and short words
+        for n in 0..4 {
+            rules.push(Rule::TogglePosition(n));
+        }
+        rules.push(Rule::TogglePermutations(8));
+
+        Self { rules }
+    }
+
+    /// Add rules after the ones already loaded
+    pub fn add_rules(&mut self, rules: impl IntoIterator<Item = Rule>) {
+        self.rules.extend(rules);
+    }
+
     /// Get all rules
     pub fn rules(&self) -> &[Rule] {
         &self.rules
     }
 
     /// Get number of rules
+    ///
+    /// Not the number of candidates per word: `LeetCombinations` and
+    /// `TogglePermutations` expand to several candidates, and rejection rules
+    /// to none.
     pub fn count(&self) -> usize {
         self.rules.len()
     }
 
     /// Apply all rules to a password and return all candidates
     pub fn generate_candidates(&self, password: &str) -> Vec<String> {
-        self.rules.iter().map(|rule| rule.apply(password)).collect()
+        self.rules
+            .iter()
+            .flat_map(|rule| rule.expand(password))
+            .collect()
+    }
+
+    /// Apply all rules to a raw password, tagging each candidate with its rule index
+    ///
+    /// Valid UTF-8 is edited per character. Other input (e.g. Latin-1 lines)
+    /// is edited per byte and keeps its original encoding.
+    pub fn generate_candidates_with_rules(&self, password: &[u8]) -> Vec<(usize, Vec<u8>)> {
+        let (text, utf8) = to_text(password);
+        self.rules
+            .iter()
+            .enumerate()
+            .flat_map(|(index, rule)| {
+                rule.expand(&text)
+                    .into_iter()
+                    .map(move |candidate| (index, to_bytes(candidate, utf8)))
+            })
+            .collect()
+    }
+}
+
+/// Write rules to a file, one per line, in the format read by `RuleEngine::from_file`
+pub fn write_rules_file<'a>(path: &str, rules: impl IntoIterator<Item = &'a Rule>) -> Result<()> {
+    let file = File::create(path)
+        .map_err(|e| CrackerError::RulesFileError(format!("Failed to create {}: {}", path, e)))?;
+
+    let mut writer = BufWriter::new(file);
+    for rule in rules {
+        writeln!(writer, "{}", rule)
+            .map_err(|e| CrackerError::RulesFileError(format!("Failed to write {}: {}", path, e)))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| CrackerError::RulesFileError(format!("Failed to write {}: {}", path, e)))?;
+
+    Ok(())
+}
+
+/// Per-rule hit counters, indexed like `RuleEngine::rules`
+pub struct RuleHits {
+    hits: Vec<AtomicU64>,
+}
+
+impl RuleHits {
+    pub fn new(rule_count: usize) -> Self {
+        Self {
+            hits: (0..rule_count).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Record a cracked password produced by the rule at `index`
+    pub fn record(&self, index: usize) {
+        if let Some(counter) = self.hits.get(index) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Rules with at least one hit, most successful first
+    pub fn hit_rules<'a>(&self, engine: &'a RuleEngine) -> Vec<(&'a Rule, u64)> {
+        let mut hit: Vec<(&Rule, u64)> = engine
+            .rules()
+            .iter()
+            .zip(self.hits.iter())
+            .map(|(rule, counter)| (rule, counter.load(Ordering::Relaxed)))
+            .filter(|(_, hits)| *hits > 0)
+            .collect();
+        hit.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+        hit
+    }
+}
+
+impl Default for RuleEngine {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -208,5 +775,134 @@ mod tests {
         assert!(matches!(Rule::parse("uppercase_first"), Some(Rule::UppercaseFirst)));
         assert!(matches!(Rule::parse("lowercase"), Some(Rule::Lowercase)));
         assert!(Rule::parse("invalid_rule").is_none());
+        assert!(matches!(Rule::parse("substitute:a:@"), Some(Rule::Substitute('a', '@'))));
+        assert!(matches!(Rule::parse("toggle:3"), Some(Rule::TogglePosition(3))));
+        assert!(matches!(Rule::parse("leet_combinations:8"), Some(Rule::LeetCombinations(8))));
+        assert!(Rule::parse("substitute:ab:@").is_none());
+    }
+
+    #[test]
+    fn test_substitution_and_toggle_rules() {
+        assert_eq!(Rule::Substitute('a', '@').apply("password"), "p@ssword");
+        assert_eq!(Rule::Leet.apply("Password"), "P@$$w0rd");
+        assert_eq!(Rule::TogglePosition(0).apply("password"), "Password");
+        assert_eq!(Rule::TogglePosition(20).apply("password"), "password");
+        assert_eq!(Rule::TogglePermutations(8).apply("PassWord"), "pASSwORD");
+
+        let leet = Rule::LeetCombinations(8).expand("sot");
+        assert_eq!(leet.len(), 7);
+        assert!(leet.contains(&"$ot".to_string()));
+        assert!(leet.contains(&"$07".to_string()));
+        assert!(!leet.contains(&"sot".to_string()));
+
+        let toggles = Rule::TogglePermutations(3).expand("ab1");
+        assert_eq!(toggles, vec!["Ab1", "aB1", "AB1"]);
+        assert!(Rule::TogglePermutations(3).expand("abcd").is_empty());
+    }
+
+    #[test]
+    fn test_positional_rules() {
+        assert_eq!(Rule::InsertAt(4, '_').apply("passw0rd"), "pass_w0rd");
+        assert_eq!(Rule::InsertAt(8, '!').apply("password"), "password!");
+        assert_eq!(Rule::InsertAt(9, '!').apply("password"), "password");
+        assert_eq!(Rule::DeleteAt(0).apply("password"), "assword");
+        assert_eq!(Rule::OverwriteAt(1, '@').apply("password"), "p@ssword");
+        assert_eq!(Rule::Truncate(4).apply("password"), "pass");
+        assert_eq!(Rule::DeleteFirst.apply("password"), "assword");
+        assert_eq!(Rule::DeleteLast.apply("password"), "passwor");
+        assert_eq!(Rule::RotateLeft.apply("password"), "asswordp");
+        assert_eq!(Rule::RotateRight.apply("password"), "dpasswor");
+        assert_eq!(Rule::Extract(4, 4).apply("password"), "word");
+        assert_eq!(Rule::Extract(20, 4).apply("password"), "password");
+
+        // Multi-byte characters are edited as whole characters
+        assert_eq!(Rule::DeleteAt(1).apply("пароль"), "проль");
+        assert_eq!(Rule::Truncate(3).apply("çağrı123"), "çağ");
+        assert_eq!(Rule::InsertAt(2, 'ß').apply("über"), "übßer");
+        assert_eq!(Rule::OverwriteAt(0, 'Ö').apply("öl"), "Öl");
+        assert_eq!(Rule::RotateRight.apply("日本語"), "語日本");
+        assert_eq!(Rule::Extract(1, 2).apply("日本語"), "本語");
+        assert!(matches!(Rule::parse("insert:3:é"), Some(Rule::InsertAt(3, 'é'))));
+        assert!(matches!(Rule::parse("extract:0:4"), Some(Rule::Extract(0, 4))));
+    }
+
+    #[test]
+    fn test_rejection_rules_and_chains() {
+        assert!(Rule::RejectShorter(8).expand("pass").is_empty());
+        assert_eq!(Rule::RejectShorter(4).expand("pass"), vec!["pass"]);
+        assert!(Rule::RejectLonger(4).expand("password").is_empty());
+        assert!(Rule::RejectUnless(CharClass::Digit).expand("password").is_empty());
+        assert!(Rule::RejectContains('@').expand("p@ss").is_empty());
+
+        let chain = Rule::parse("uppercase_first append_digit:1 reject_shorter:9").unwrap();
+        assert_eq!(chain.expand("password"), vec!["Password1"]);
+        assert!(chain.expand("pass").is_empty());
+        let chain = Rule::Chain(vec![Rule::Reverse, Rule::RejectShorter(8)]);
+        assert!(chain.expand("abc").is_empty());
+        assert!(Rule::parse("lowercase bogus").is_none());
+        assert!(matches!(
+            Rule::parse("reject_unless:upper"),
+            Some(Rule::RejectUnless(CharClass::Upper))
+        ));
+    }
+
+    #[test]
+    fn test_byte_candidates() {
+        let apply_bytes = |rule: Rule, password: &[u8]| {
+            let engine = RuleEngine { rules: vec![rule] };
+            engine.generate_candidates_with_rules(password).remove(0).1
+        };
+        assert_eq!(apply_bytes(Rule::Uppercase, b"p\xe4ss"), b"P\xc4SS");
+        assert_eq!(apply_bytes(Rule::DeleteAt(1), b"p\xe4ss"), b"pss");
+        assert_eq!(apply_bytes(Rule::Reverse, "año".as_bytes()), "oña".as_bytes());
+
+        let engine = RuleEngine {
+            rules: vec![Rule::None, Rule::AppendDigit(1), Rule::RejectShorter(8)],
+        };
+        assert_eq!(
+            engine.generate_candidates_with_rules(b"\xffpw"),
+            vec![(0, b"\xffpw".to_vec()), (1, b"\xffpw1".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_rule_display_round_trip() {
+        let lines = [
+            "append_digit:7",
+            "substitute:a:@",
+            "insert:2:é",
+            "extract:1:3",
+            "reject_unless:digit",
+            "uppercase_first append_year:2024 reject_shorter:8",
+        ];
+        for line in lines {
+            let rule = Rule::parse(line).unwrap();
+            assert_eq!(rule.to_string(), line);
+            assert_eq!(Rule::parse(&rule.to_string()), Some(rule));
+        }
+    }
+
+    #[test]
+    fn test_escaped_char_arguments() {
+        assert_eq!(Rule::parse("substitute:\\s:\\:"), Some(Rule::Substitute(' ', ':')));
+        assert_eq!(Rule::parse("insert:0:\\\\"), Some(Rule::InsertAt(0, '\\')));
+        assert_eq!(Rule::parse("append_special:\\u{9}"), Some(Rule::AppendSpecial('\t')));
+        assert!(Rule::parse("substitute:::").is_none());
+
+        let chain = Rule::Chain(vec![
+            Rule::Substitute(':', ' '),
+            Rule::InsertAt(2, ':'),
+            Rule::OverwriteAt(0, '\\'),
+            Rule::RejectContains(' '),
+            Rule::PrependSpecial('\u{a0}'),
+        ]);
+        assert_eq!(
+            chain.to_string(),
+            concat!(
+                "substitute:\\::\\s insert:2:\\: overwrite:0:\\\\ ",
+                "reject_contains:\\s prepend_special:\\u{a0}"
+            )
+        );
+        assert_eq!(Rule::parse(&chain.to_string()), Some(chain));
     }
 }
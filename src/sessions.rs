@@ -0,0 +1,176 @@
+use crate::checkpoint::{Checkpoint, Recovered};
+use crate::errors::{CrackerError, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A saved session as shown by `sessions list`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub name: String,
+    /// The target hash, or the number of hashes
+    pub target: String,
+    /// Percentage of the keyspace tested, if known
+    pub progress: Option<f64>,
+    /// Timestamp of the last checkpoint
+    pub updated: String,
+}
+
+/// Directory holding named sessions
+///
+/// `$XDG_STATE_HOME/pbkdf2_cracker/sessions`, or `~/.local/state/...` when
+/// `XDG_STATE_HOME` is not set.
+pub fn sessions_dir() -> Result<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))
+        .ok_or_else(|| {
+            CrackerError::SessionError("No state directory: set XDG_STATE_HOME or HOME".to_string())
+        })?;
+    Ok(state_dir.join("pbkdf2_cracker").join("sessions"))
+}
+
+/// Checkpoint file of a named session in `dir`, creating `dir` if needed
+pub fn session_path(dir: &Path, name: &str) -> Result<String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(CrackerError::SessionError(format!(
+            "Invalid session name '{}' (use letters, digits, '-', '_' and '.')",
+            name
+        )));
+    }
+
+    fs::create_dir_all(dir).map_err(|e| {
+        CrackerError::SessionError(format!("Failed to create {}: {}", dir.display(), e))
+    })?;
+    Ok(dir
+        .join(format!("{}.json", name))
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Load a named session's latest checkpoint
+pub fn load_session(dir: &Path, name: &str) -> Result<Checkpoint> {
+    match Checkpoint::recover(&session_path(dir, name)?)? {
+        Recovered::Latest(checkpoint) | Recovered::Backup { checkpoint, .. } => Ok(checkpoint),
+        Recovered::Missing => Err(CrackerError::SessionError(format!(
+            "No session named '{}'",
+            name
+        ))),
+    }
+}
+
+/// Every saved session in `dir`, sorted by name
+pub fn list_sessions(dir: &Path) -> Result<Vec<SessionSummary>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(CrackerError::SessionError(format!(
+                "Failed to read {}: {}",
+                dir.display(),
+                e
+            )))
+        }
+    };
+
+    let mut sessions = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(file_name) => match file_name.strip_suffix(".json") {
+                Some(name) => name.to_string(),
+                None => continue,
+            },
+            None => continue,
+        };
+
+        // An unreadable session is still listed so it can be deleted
+        let summary = match load_session(dir, &name) {
+            Ok(checkpoint) => {
+                let hashes = checkpoint
+                    .session
+                    .as_ref()
+                    .map(|session| session.hashes.as_slice())
+                    .unwrap_or_default();
+                let target = match hashes {
+                    [] => "(unknown)".to_string(),
+                    [hash] => hash.clone(),
+                    hashes => format!("{} hashes", hashes.len()),
+                };
+                SessionSummary {
+                    name,
+                    target,
+                    progress: checkpoint.progress,
+                    updated: checkpoint.timestamp,
+                }
+            }
+            Err(e) => SessionSummary {
+                name,
+                target: format!("(unreadable: {})", e),
+                progress: None,
+                updated: String::new(),
+            },
+        };
+        sessions.push(summary);
+    }
+
+    sessions.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(sessions)
+}
+
+/// Delete a named session and its backup
+pub fn delete_session(dir: &Path, name: &str) -> Result<()> {
+    let path = session_path(dir, name)?;
+    if !Path::new(&path).exists() && !Path::new(&Checkpoint::backup_path(&path)).exists() {
+        return Err(CrackerError::SessionError(format!(
+            "No session named '{}'",
+            name
+        )));
+    }
+    Checkpoint::delete(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_and_delete_sessions() {
+        let dir =
+            std::env::temp_dir().join(format!("pbkdf2_cracker_{}_sessions", std::process::id()));
+        assert_eq!(list_sessions(&dir).unwrap(), Vec::new());
+        assert!(session_path(&dir, "../escape").is_err());
+        assert!(session_path(&dir, ".hidden").is_err());
+
+        let mut checkpoint = Checkpoint::new(0, "words.txt", 50, None, 0, 50);
+        checkpoint.progress = Some(25.0);
+        checkpoint
+            .save(&session_path(&dir, "beta").unwrap())
+            .unwrap();
+        checkpoint
+            .save(&session_path(&dir, "alpha").unwrap())
+            .unwrap();
+        // Saving twice leaves a backup, which is not a session of its own
+        checkpoint
+            .save(&session_path(&dir, "alpha").unwrap())
+            .unwrap();
+
+        let sessions = list_sessions(&dir).unwrap();
+        let names: Vec<&str> = sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha", "beta"]);
+        assert_eq!(sessions[0].progress, Some(25.0));
+        assert_eq!(sessions[0].target, "(unknown)");
+        assert_eq!(load_session(&dir, "beta").unwrap().wordlist_offset, 50);
+
+        delete_session(&dir, "alpha").unwrap();
+        assert!(delete_session(&dir, "alpha").is_err());
+        assert!(load_session(&dir, "alpha").is_err());
+        assert_eq!(list_sessions(&dir).unwrap().len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,16 @@
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::report;
+use crate::say;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
+use std::fmt::Write;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 /// Statistics tracker for cracking progress
+///
+/// Attempts count hash derivations; the progress bar, if any, counts
+/// (word, rule) pairs of the keyspace, so filtered and duplicate candidates
+/// still move it forward.
 pub struct CrackingStats {
     attempts: Arc<AtomicU64>,
     start_time: Instant,
@@ -12,93 +19,152 @@ pub struct CrackingStats {
 
 impl CrackingStats {
     pub fn new(total_candidates: Option<u64>) -> Self {
+        let attempts = Arc::new(AtomicU64::new(0));
+        let start_time = Instant::now();
         let progress_bar = total_candidates.map(|total| {
             let pb = ProgressBar::new(total);
+            let template = if report::is_plain() {
+                "[{elapsed_precise}] [{bar:40}] {percent}% {human_pos}/{human_len} ({rate}, ETA {eta}) {msg}"
+            } else {
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% {human_pos}/{human_len} ({rate}, ETA {eta}) {msg}"
+            };
+            // The bar's own rate counts pairs, so candidates come from the attempts
+            let counted = Arc::clone(&attempts);
             pb.set_style(
                 ProgressStyle::default_bar()
-                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({per_sec}) {msg}")
+                    .template(template)
                     .unwrap()
+                    .with_key("rate", move |_: &ProgressState, w: &mut dyn Write| {
+                        let rate = per_sec(counted.load(Ordering::Relaxed), start_time.elapsed());
+                        let _ = write!(w, "{:.0} c/s", rate);
+                    })
                     .progress_chars("#>-"),
-                    );
-                    pb
-                    });
-        Self {
-                attempts: Arc::new(AtomicU64::new(0)),
-                start_time: Instant::now(),
-                progress_bar,
+            );
+            // The bar still tracks progress for JSON status events
+            if !report::is_human() {
+                pb.set_draw_target(ProgressDrawTarget::hidden());
             }
+            pb
+        });
+        Self {
+            attempts,
+            start_time,
+            progress_bar,
         }
+    }
 
-        /// Increment attempt counter
-        pub fn increment(&self, count: u64) {
-            self.attempts.fetch_add(count, Ordering::Relaxed);
-            if let Some(ref pb) = self.progress_bar {
-                pb.inc(count);
-            }
-        }
+    /// Increment attempt counter
+    pub fn increment(&self, count: u64) {
+        self.attempts.fetch_add(count, Ordering::Relaxed);
+    }
 
-        /// Get total attempts
-        pub fn attempts(&self) -> u64 {
-            self.attempts.load(Ordering::Relaxed)
+    /// Move the progress bar over `count` (word, rule) pairs of the keyspace
+    pub fn advance(&self, count: u64) {
+        if let Some(ref pb) = self.progress_bar {
+            pb.inc(count);
         }
+    }
 
-        /// Get attempts per second
-        pub fn rate(&self) -> f64 {
-            let attempts = self.attempts() as f64;
-            let elapsed = self.start_time.elapsed().as_secs_f64();
-            if elapsed > 0.0 {
-                attempts / elapsed
-            } else {
-                0.0
-            }
-        }
+    /// Get total attempts
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Pairs of the keyspace done so far, if there is a progress bar
+    pub fn progress(&self) -> Option<u64> {
+        self.progress_bar.as_ref().map(ProgressBar::position)
+    }
+
+    /// Size of the keyspace, if there is a progress bar
+    pub fn keyspace(&self) -> Option<u64> {
+        self.progress_bar.as_ref().and_then(ProgressBar::length)
+    }
+
+    /// Estimated time left, if there is a progress bar
+    pub fn eta(&self) -> Option<Duration> {
+        self.progress_bar.as_ref().map(ProgressBar::eta)
+    }
 
-        /// Get elapsed time
-        pub fn elapsed(&self) -> Duration {
-            self.start_time.elapsed()
+    /// Current progress bar message (the base word being tested)
+    pub fn message(&self) -> String {
+        self.progress_bar
+            .as_ref()
+            .map(ProgressBar::message)
+            .unwrap_or_default()
+    }
+
+    /// Get attempts per second
+    pub fn rate(&self) -> f64 {
+        per_sec(self.attempts(), self.elapsed())
+    }
+
+    /// Get elapsed time
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    /// Set progress bar message
+    pub fn set_message(&self, msg: String) {
+        if let Some(ref pb) = self.progress_bar {
+            pb.set_message(msg);
         }
+    }
 
-        /// Set progress bar message
-        pub fn set_message(&self, msg: String) {
-            if let Some(ref pb) = self.progress_bar {
-                pb.set_message(msg);
-            }
+    /// Run `f` with the progress bar hidden, for printing other output
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        match self.progress_bar {
+            Some(ref pb) => pb.suspend(f),
+            None => f(),
         }
+    }
 
-        /// Finish progress bar
-        pub fn finish(&self) {
-            if let Some(ref pb) = self.progress_bar {
-                pb.finish_with_message("Done");
-            }
+    /// Finish progress bar
+    pub fn finish(&self) {
+        if let Some(ref pb) = self.progress_bar {
+            pb.finish_with_message("Done");
         }
+    }
 
-        /// Print final statistics
-        pub fn print_summary(&self) {
-            let attempts = self.attempts();
-            let elapsed = self.elapsed();
-            let rate = self.rate();
-
-            println!();
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("📊 Cracking Statistics");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("⏱  Total Time:     {:?}", elapsed);
-            println!("🔢 Total Attempts:  {}", attempts);
-            println!("⚡ Average Rate:    {:.2} H/s", rate);
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    /// Stop the progress bar where it is
+    pub fn abandon(&self) {
+        if let Some(ref pb) = self.progress_bar {
+            pb.abandon();
         }
+    }
+
+    /// Print final statistics
+    pub fn print_summary(&self) {
+        let attempts = self.attempts();
+        let elapsed = self.elapsed();
+        let rate = self.rate();
+
+        say!();
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        say!("📊 Cracking Statistics");
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        say!("⏱  Total Time:     {}", format_duration(elapsed));
+        say!("🔢 Total Attempts:  {}", attempts);
+        say!("⚡ Average Rate:    {:.2} H/s", rate);
+        say!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    }
 }
-/// Clone-able stats handle for sharing across threads
-#[derive(Clone)]
-pub struct StatsHandle {
-attempts: Arc<AtomicU64>,
-}
-impl StatsHandle {
-pub fn new(stats: &CrackingStats) -> Self {
-Self {
-attempts: Arc::clone(&stats.attempts),
-}
-}pub fn increment(&self, count: u64) {
-    self.attempts.fetch_add(count, Ordering::Relaxed);
+
+/// Average rate of `count` events over `elapsed`
+fn per_sec(count: u64, elapsed: Duration) -> f64 {
+    let elapsed = elapsed.as_secs_f64();
+    if elapsed > 0.0 {
+        count as f64 / elapsed
+    } else {
+        0.0
+    }
 }
+
+/// Format a duration as `1h 02m 03s`, `2m 03s` or `3.4s`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, _) => format!("{:.1}s", duration.as_secs_f64()),
+        (0, minutes, secs) => format!("{}m {:02}s", minutes, secs),
+        (hours, minutes, secs) => format!("{}h {:02}m {:02}s", hours, minutes, secs),
+    }
 }
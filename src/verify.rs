@@ -1,32 +1,40 @@
 use crate::cracker::Pbkdf2Cracker;
+use crate::encoding::decode_hex_entry;
 use crate::errors::Result;
 use crate::parser::ParsedHash;
+use crate::report;
+use crate::{say, say_err};
 
 /// Verify a password against a hash
+/// Passwords in `$HEX[...]` form are decoded to raw bytes first
 pub fn verify_password(hash: &str, password: &str) -> Result<bool> {
     let parsed = ParsedHash::parse(hash)?;
     let cracker = Pbkdf2Cracker::new(parsed);
-    Ok(cracker.test_password(password))
+    let password =
+        decode_hex_entry(password.as_bytes()).unwrap_or_else(|| password.as_bytes().to_vec());
+    Ok(cracker.test_password(&password))
 }
 
 /// Verify and print result
+/// In the quiet mode only a match is printed, as `hash:password`
 pub fn verify_and_report(hash: &str, password: &str) -> i32 {
-    println!("🔍 Verifying password...");
-    println!("Hash: {}", hash);
-    println!("Password: {}", password);
-    println!();
+    say!("🔍 Verifying password...");
+    say!("Hash: {}", hash);
+    say!("Password: {}", password);
+    say!();
 
     match verify_password(hash, password) {
         Ok(true) => {
-            println!("✅ SUCCESS: Password matches!");
+            say!("✅ SUCCESS: Password matches!");
+            report::cracked(hash, password);
             0 // Exit code 0 for match
         }
         Ok(false) => {
-            println!("❌ FAILED: Password does not match");
+            say!("❌ FAILED: Password does not match");
             2 // Exit code 2 for no match
         }
         Err(e) => {
-            eprintln!("❌ ERROR: {}", e);
+            say_err!("❌ ERROR: {}", e);
             1 // Exit code 1 for error
         }
     }
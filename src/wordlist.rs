@@ -1,11 +1,232 @@
+use crate::encoding::decode_hex_entry;
 use crate::errors::{CrackerError, Result};
+use crate::index::{WordlistIndex, INDEX_INTERVAL};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use xz2::read::XzDecoder;
+
+/// Compression format of a wordlist, detected from its magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression format from the first bytes of a file
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Open a wordlist, transparently decompressing it if needed
+fn open_wordlist(path: &str) -> Result<(Box<dyn BufRead + Send>, Compression)> {
+    let file = File::open(path)
+        .map_err(|e| CrackerError::WordlistNotFound(format!("Failed to open {}: {}", path, e)))?;
+
+    // Peek at the magic bytes without consuming them
+    let mut reader = BufReader::new(file);
+    let compression =
+        Compression::detect(reader.fill_buf().map_err(|e| {
+            CrackerError::WordlistReadError(format!("Failed to read {}: {}", path, e))
+        })?);
+
+    let reader: Box<dyn BufRead + Send> = match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+        Compression::Xz => Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader))),
+        Compression::Zstd => {
+            let decoder = zstd::Decoder::with_buffer(reader).map_err(|e| {
+                CrackerError::WordlistReadError(format!("Failed to read {}: {}", path, e))
+            })?;
+            Box::new(BufReader::new(decoder))
+        }
+    };
+
+    Ok((reader, compression))
+}
+
+/// Count lines in a stream without decoding them, including a final
+/// line with no trailing newline
+fn count_lines(mut reader: impl BufRead) -> std::io::Result<u64> {
+    let mut count = 0;
+    let mut last_byte = b'\n';
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        count += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        last_byte = buf[buf.len() - 1];
+        let len = buf.len();
+        reader.consume(len);
+    }
+
+    if last_byte != b'\n' {
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Open a plain wordlist positioned at a byte offset saved in a checkpoint
+///
+/// Returns `None` if the offset does not land at the start of a line, which
+/// means the file changed since the checkpoint and the caller must fall back
+/// to skipping lines.
+fn open_at_byte(path: &str, byte_offset: u64) -> Result<Option<BufReader<File>>> {
+    let mut file = File::open(path)
+        .map_err(|e| CrackerError::WordlistNotFound(format!("Failed to open {}: {}", path, e)))?;
+    let seek_error = |e: std::io::Error| {
+        CrackerError::WordlistReadError(format!("Failed to seek {}: {}", path, e))
+    };
+
+    let len = file.metadata().map_err(seek_error)?.len();
+    if byte_offset > len {
+        return Ok(None);
+    }
+
+    if byte_offset > 0 {
+        let mut previous = [0u8; 1];
+        file.seek(SeekFrom::Start(byte_offset - 1))
+            .map_err(seek_error)?;
+        file.read_exact(&mut previous).map_err(seek_error)?;
+        if previous[0] != b'\n' {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(BufReader::new(file)))
+}
+
+/// Expand wordlist arguments into the ordered list of files to read
+///
+/// Files are kept in the order given. A directory expands to the regular
+/// files directly inside it, sorted by name; index sidecars (`*.idx`) and
+/// hidden files are skipped.
+pub fn expand_wordlist_paths(paths: &[String]) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let metadata = std::fs::metadata(path).map_err(|e| {
+            CrackerError::WordlistNotFound(format!("Failed to open {}: {}", path, e))
+        })?;
+        if !metadata.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+
+        let entries = std::fs::read_dir(path).map_err(|e| {
+            CrackerError::WordlistReadError(format!("Failed to list {}: {}", path, e))
+        })?;
+        let mut dir_files = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| {
+                CrackerError::WordlistReadError(format!("Failed to list {}: {}", path, e))
+            })?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let is_file = entry.path().is_file();
+            if is_file && !name.starts_with('.') && !name.ends_with(".idx") {
+                dir_files.push(entry.path().to_string_lossy().into_owned());
+            }
+        }
+        dir_files.sort();
+        files.extend(dir_files);
+    }
+
+    if files.is_empty() {
+        return Err(CrackerError::WordlistNotFound(format!(
+            "No wordlist files in {}",
+            paths.join(", ")
+        )));
+    }
+    Ok(files)
+}
+
+/// A wordlist entry with its position in the file
+///
+/// Entries from a memory-mapped wordlist borrow the word from the mapping;
+/// streamed entries own it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordEntry<'a> {
+    /// Zero-based line number
+    pub line: u64,
+    /// Byte offset of the start of the line (in the decompressed stream)
+    pub byte_offset: u64,
+    /// Raw candidate bytes; lines need not be valid UTF-8
+    pub word: Cow<'a, [u8]>,
+}
+
+/// How raw wordlist lines become candidate words
+///
+/// By default only the line terminator (`\n` or `\r\n`) is removed, so
+/// leading and trailing spaces and tabs stay part of the password, and empty
+/// lines are skipped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineOptions {
+    /// Also trim surrounding ASCII whitespace
+    pub trim_whitespace: bool,
+    /// Yield empty lines as empty-string candidates instead of skipping them
+    pub keep_empty: bool,
+}
+
+impl LineOptions {
+    /// Turn a raw line into a candidate word, decoding `$HEX[...]` entries
+    /// Returns `None` for lines that should be skipped
+    pub fn to_word<'a>(&self, raw: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        let mut word = raw.strip_suffix(b"\n").unwrap_or(raw);
+        word = word.strip_suffix(b"\r").unwrap_or(word);
+        if self.trim_whitespace {
+            word = word.trim_ascii();
+        }
+        if word.is_empty() && !self.keep_empty {
+            return None;
+        }
+        match decode_hex_entry(word) {
+            Some(decoded) => Some(Cow::Owned(decoded)),
+            None => Some(Cow::Borrowed(word)),
+        }
+    }
+}
 
 /// Wordlist reader with offset support for resuming
 pub struct WordlistReader {
     path: String,
     current_offset: u64,
+    byte_offset: Option<u64>,
+    use_index: bool,
+    line_options: LineOptions,
 }
 
 impl WordlistReader {
@@ -13,6 +234,9 @@ impl WordlistReader {
         Self {
             path,
             current_offset: 0,
+            byte_offset: None,
+            use_index: true,
+            line_options: LineOptions::default(),
         }
     }
 
@@ -21,52 +245,162 @@ impl WordlistReader {
         Self {
             path,
             current_offset: offset,
+            byte_offset: None,
+            use_index: true,
+            line_options: LineOptions::default(),
         }
     }
 
+    /// Create reader starting from a line whose byte offset is known
+    ///
+    /// Plain files seek straight to `byte_offset`; compressed streams
+    /// ignore it and skip `line` lines instead.
+    pub fn from_position(path: String, line: u64, byte_offset: Option<u64>) -> Self {
+        Self {
+            path,
+            current_offset: line,
+            byte_offset,
+            use_index: true,
+            line_options: LineOptions::default(),
+        }
+    }
+
+    /// Enable or disable the persistent index cache (enabled by default)
+    pub fn with_index(mut self, enabled: bool) -> Self {
+        self.use_index = enabled;
+        self
+    }
+
+    /// Set how lines are turned into words (terminator-only stripping by default)
+    pub fn with_line_options(mut self, options: LineOptions) -> Self {
+        self.line_options = options;
+        self
+    }
+
+    /// Check whether a valid index exists, so counting will be instant
+    pub fn has_index(&self) -> bool {
+        self.use_index && WordlistIndex::load(&self.path).is_some()
+    }
+
     /// Get current offset (number of words processed)
     pub fn offset(&self) -> u64 {
         self.current_offset
     }
 
+    /// Detect the compression format of the wordlist
+    pub fn compression(&self) -> Result<Compression> {
+        open_wordlist(&self.path).map(|(_, compression)| compression)
+    }
+
     /// Read all words from the wordlist starting from current offset
     pub fn read_words(&mut self) -> Result<WordlistIterator> {
-        let file = File::open(&self.path).map_err(|e| {
-            CrackerError::WordlistNotFound(format!("Failed to open {}: {}", self.path, e))
-        })?;
+        let (reader, compression) = open_wordlist(&self.path)?;
+
+        if let (Compression::None, Some(byte_offset)) = (compression, self.byte_offset) {
+            if self.current_offset > 0 {
+                if let Some(reader) = open_at_byte(&self.path, byte_offset)? {
+                    return Ok(WordlistIterator {
+                        reader: Box::new(reader),
+                        current_line: self.current_offset,
+                        current_byte: byte_offset,
+                        start_offset: self.current_offset,
+                        seeked: true,
+                        line_options: self.line_options,
+                    });
+                }
+            }
+        }
 
         Ok(WordlistIterator {
-            reader: BufReader::new(file),
+            reader,
             current_line: 0,
+            current_byte: 0,
             start_offset: self.current_offset,
+            seeked: false,
+            line_options: self.line_options,
         })
     }
 
-    /// Count total words in wordlist
-    pub fn count_words(&self) -> Result<u64> {
+    /// Memory-map the wordlist if it is an uncompressed regular file
+    pub fn map(&self) -> Result<Option<MmapWordlist>> {
         let file = File::open(&self.path).map_err(|e| {
             CrackerError::WordlistNotFound(format!("Failed to open {}: {}", self.path, e))
         })?;
 
-        let reader = BufReader::new(file);
-        let count = reader.lines().count() as u64;
-        Ok(count)
+        let is_file = file.metadata().map(|m| m.is_file()).unwrap_or(false);
+        if !is_file || self.compression()? != Compression::None {
+            return Ok(None);
+        }
+
+        // SAFETY: the mapping is read-only. Truncating the wordlist while it is
+        // being cracked is unsupported, as with any mmap-based tool.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| {
+            CrackerError::WordlistReadError(format!("Failed to map {}: {}", self.path, e))
+        })?;
+
+        let index = if self.use_index {
+            WordlistIndex::load(&self.path)
+        } else {
+            None
+        };
+
+        Ok(Some(MmapWordlist {
+            mmap,
+            index,
+            line_options: self.line_options,
+        }))
+    }
+
+    /// Count total words in wordlist
+    ///
+    /// Uses the index cache when it is valid; otherwise counts the lines and
+    /// writes a fresh index for the next run.
+    pub fn count_words(&self) -> Result<u64> {
+        if self.use_index {
+            if let Some(index) = WordlistIndex::load(&self.path) {
+                return Ok(index.total_lines);
+            }
+        }
+
+        let (total, offsets) = if let Some(mapped) = self.map()? {
+            (mapped.count_lines(), mapped.line_offsets(INDEX_INTERVAL))
+        } else {
+            let (reader, _) = open_wordlist(&self.path)?;
+            let total = count_lines(reader).map_err(|e| {
+                CrackerError::WordlistReadError(format!("Failed to read {}: {}", self.path, e))
+            })?;
+            // Compressed streams cannot seek, so only the count is worth caching
+            (total, Vec::new())
+        };
+
+        if self.use_index {
+            // The index is only a cache; failing to write it is not an error
+            if let Some(index) = WordlistIndex::new(&self.path, total, offsets) {
+                index.save();
+            }
+        }
+
+        Ok(total)
     }
 }
 
 /// Iterator over wordlist lines
 pub struct WordlistIterator {
-    reader: BufReader<File>,
+    reader: Box<dyn BufRead + Send>,
     current_line: u64,
+    current_byte: u64,
     start_offset: u64,
+    seeked: bool,
+    line_options: LineOptions,
 }
 
 impl WordlistIterator {
     /// Skip to the start offset
     fn skip_to_offset(&mut self) -> Result<()> {
+        let mut line = Vec::new();
         while self.current_line < self.start_offset {
-            let mut line = String::new();
-            let bytes_read = self.reader.read_line(&mut line).map_err(|e| {
+            line.clear();
+            let bytes_read = self.reader.read_until(b'\n', &mut line).map_err(|e| {
                 CrackerError::WordlistReadError(format!("Failed to skip line: {}", e))
             })?;
 
@@ -75,6 +409,7 @@ impl WordlistIterator {
             }
 
             self.current_line += 1;
+            self.current_byte += bytes_read as u64;
         }
         Ok(())
     }
@@ -82,10 +417,20 @@ impl WordlistIterator {
     pub fn current_line(&self) -> u64 {
         self.current_line
     }
+
+    /// Byte offset of the next unread line
+    pub fn current_byte(&self) -> u64 {
+        self.current_byte
+    }
+
+    /// Whether the iterator resumed by seeking instead of skipping lines
+    pub fn seeked(&self) -> bool {
+        self.seeked
+    }
 }
 
 impl Iterator for WordlistIterator {
-    type Item = Result<(u64, String)>;
+    type Item = Result<WordEntry<'static>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         // Skip to offset on first call
@@ -95,24 +440,463 @@ impl Iterator for WordlistIterator {
             }
         }
 
-        let mut line = String::new();
-        match self.reader.read_line(&mut line) {
-            Ok(0) => None, // EOF
-            Ok(_) => {
-                let word = line.trim().to_string();
-                let offset = self.current_line;
-                self.current_line += 1;
+        loop {
+            let mut line = Vec::new();
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(0) => return None, // EOF
+                Ok(bytes_read) => {
+                    let word = self.line_options.to_word(&line).map(Cow::into_owned);
+                    let entry_line = self.current_line;
+                    let entry_byte = self.current_byte;
+                    self.current_line += 1;
+                    self.current_byte += bytes_read as u64;
 
-                if word.is_empty() {
-                    self.next() // Skip empty lines
+                    if let Some(word) = word {
+                        return Some(Ok(WordEntry {
+                            line: entry_line,
+                            byte_offset: entry_byte,
+                            word: Cow::Owned(word),
+                        }));
+                    }
+                }
+                Err(e) => {
+                    return Some(Err(CrackerError::WordlistReadError(format!(
+                        "Failed to read line: {}",
+                        e
+                    ))))
+                }
+            }
+        }
+    }
+}
+
+/// Memory-mapped uncompressed wordlist
+///
+/// Lines are split directly on the mapped bytes, so reading a word costs no
+/// allocation or copy, and counting lines is a single SIMD newline scan.
+pub struct MmapWordlist {
+    mmap: Mmap,
+    index: Option<WordlistIndex>,
+    line_options: LineOptions,
+}
+
+impl MmapWordlist {
+    /// Count lines, including a final line with no trailing newline
+    pub fn count_lines(&self) -> u64 {
+        let data = &self.mmap[..];
+        let newlines = memchr::memchr_iter(b'\n', data).count() as u64;
+        match data.last() {
+            Some(b'\n') | None => newlines,
+            Some(_) => newlines + 1,
+        }
+    }
+
+    /// Byte offsets of every `interval`-th line, starting with line 0
+    pub fn line_offsets(&self, interval: u64) -> Vec<u64> {
+        let data = &self.mmap[..];
+        let mut offsets = vec![0];
+        for (n, newline) in memchr::memchr_iter(b'\n', data).enumerate() {
+            let next_line = n as u64 + 1;
+            if next_line.is_multiple_of(interval) && newline + 1 < data.len() {
+                offsets.push(newline as u64 + 1);
+            }
+        }
+        offsets
+    }
+
+    /// Iterate raw lines, including their `\n`, starting at a line
+    ///
+    /// A `byte_offset` that points at the start of a line is used directly;
+    /// otherwise the start is found by scanning newlines, from the nearest
+    /// indexed line if an index is available, or from the beginning.
+    pub fn lines_from(&self, line: u64, byte_offset: Option<u64>) -> MmapLines<'_> {
+        let data = &self.mmap[..];
+
+        let at_line_start = |offset: u64| {
+            offset == 0 || (offset <= data.len() as u64 && data[offset as usize - 1] == b'\n')
+        };
+        let pos = match byte_offset {
+            Some(offset) if line > 0 && at_line_start(offset) => offset as usize,
+            _ if line == 0 => 0,
+            _ => {
+                let (start_line, start_byte) = self
+                    .index
+                    .as_ref()
+                    .and_then(|index| index.nearest(line))
+                    .unwrap_or((0, 0));
+                let start_byte = (start_byte as usize).min(data.len());
+                let remaining = line - start_line;
+                if remaining == 0 {
+                    start_byte
                 } else {
-                    Some(Ok((offset, word)))
+                    memchr::memchr_iter(b'\n', &data[start_byte..])
+                        .nth(remaining as usize - 1)
+                        .map(|newline| start_byte + newline + 1)
+                        .unwrap_or(data.len())
                 }
             }
-            Err(e) => Some(Err(CrackerError::WordlistReadError(format!(
-                "Failed to read line: {}",
-                e
-            )))),
+        };
+
+        MmapLines {
+            data,
+            pos,
+            line,
+            seeked: byte_offset.is_some_and(|offset| line > 0 && at_line_start(offset)),
+        }
+    }
+
+    /// Iterate words starting at a line, borrowing from the mapping
+    pub fn words_from(&self, line: u64, byte_offset: Option<u64>) -> MmapWords<'_> {
+        MmapWords {
+            lines: self.lines_from(line, byte_offset),
+            line_options: self.line_options,
+        }
+    }
+}
+
+/// A raw line from a memory-mapped wordlist
+/// `bytes` keeps the terminator; `LineOptions::to_word` removes it, as for
+/// streamed lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmapLine<'a> {
+    pub line: u64,
+    pub byte_offset: u64,
+    pub bytes: &'a [u8],
+}
+
+/// Iterator over raw lines of a memory-mapped wordlist
+pub struct MmapLines<'a> {
+    data: &'a [u8],
+    pos: usize,
+    line: u64,
+    seeked: bool,
+}
+
+impl<'a> MmapLines<'a> {
+    /// Whether the iterator started from the saved byte offset
+    pub fn seeked(&self) -> bool {
+        self.seeked
+    }
+}
+
+impl<'a> Iterator for MmapLines<'a> {
+    type Item = MmapLine<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+
+        let rest = &self.data[self.pos..];
+        let consumed = match memchr::memchr(b'\n', rest) {
+            Some(end) => end + 1,
+            None => rest.len(),
+        };
+        let bytes = &rest[..consumed];
+
+        let entry = MmapLine {
+            line: self.line,
+            byte_offset: self.pos as u64,
+            bytes,
+        };
+        self.pos += consumed;
+        self.line += 1;
+        Some(entry)
+    }
+}
+
+/// Iterator over words of a memory-mapped wordlist, same semantics as `WordlistIterator`
+pub struct MmapWords<'a> {
+    lines: MmapLines<'a>,
+    line_options: LineOptions,
+}
+
+impl<'a> MmapWords<'a> {
+    pub fn seeked(&self) -> bool {
+        self.lines.seeked()
+    }
+}
+
+impl<'a> Iterator for MmapWords<'a> {
+    type Item = Result<WordEntry<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for raw in self.lines.by_ref() {
+            if let Some(word) = self.line_options.to_word(raw.bytes) {
+                return Some(Ok(WordEntry {
+                    line: raw.line,
+                    byte_offset: raw.byte_offset,
+                    word,
+                }));
+            }
+        }
+        None
+    }
+}
+
+/// Words from either a memory-mapped or a streamed wordlist
+pub enum WordSource<'a> {
+    Mapped(MmapWords<'a>),
+    Streamed(WordlistIterator),
+}
+
+impl<'a> WordSource<'a> {
+    /// Whether the source resumed from a saved byte offset
+    pub fn seeked(&self) -> bool {
+        match self {
+            WordSource::Mapped(words) => words.seeked(),
+            WordSource::Streamed(words) => words.seeked(),
+        }
+    }
+}
+
+impl<'a> Iterator for WordSource<'a> {
+    type Item = Result<WordEntry<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            WordSource::Mapped(words) => words.next(),
+            WordSource::Streamed(words) => words.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_temp(name: &str, data: &[u8]) -> String {
+        let path =
+            std::env::temp_dir().join(format!("pbkdf2_cracker_{}_{}", std::process::id(), name));
+        File::create(&path).unwrap().write_all(data).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_compressed_wordlists() {
+        let plain = b"alpha\nbeta\ngamma\ndelta";
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(plain).unwrap();
+        let mut bzip = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip.write_all(plain).unwrap();
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(plain).unwrap();
+
+        let cases = [
+            ("plain.txt", plain.to_vec(), Compression::None),
+            ("list.gz", gzip.finish().unwrap(), Compression::Gzip),
+            ("list.bz2", bzip.finish().unwrap(), Compression::Bzip2),
+            ("list.xz", xz.finish().unwrap(), Compression::Xz),
+            (
+                "list.zst",
+                zstd::encode_all(&plain[..], 3).unwrap(),
+                Compression::Zstd,
+            ),
+        ];
+
+        for (name, data, expected) in cases {
+            let path = write_temp(name, &data);
+            let mut reader = WordlistReader::from_offset(path.clone(), 2).with_index(false);
+            assert_eq!(reader.compression().unwrap(), expected);
+            assert_eq!(reader.count_words().unwrap(), 4);
+
+            let words: Vec<(u64, u64, Vec<u8>)> = reader
+                .read_words()
+                .unwrap()
+                .map(|w| w.unwrap())
+                .map(|w| (w.line, w.byte_offset, w.word.into_owned()))
+                .collect();
+            assert_eq!(
+                words,
+                vec![(2, 11, b"gamma".to_vec()), (3, 17, b"delta".to_vec())]
+            );
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_resume_from_byte_offset() {
+        let path = write_temp("seek.txt", b"one\ntwo\nthree\nfour\n");
+
+        // A valid offset seeks straight to the line
+        let mut reader = WordlistReader::from_position(path.clone(), 2, Some(8));
+        let mut words = reader.read_words().unwrap();
+        let entry = words.next().unwrap().unwrap();
+        assert!(words.seeked());
+        assert_eq!(
+            (entry.line, entry.byte_offset, entry.word.as_ref()),
+            (2, 8, &b"three"[..])
+        );
+
+        // An offset in the middle of a line falls back to skipping lines
+        let mut reader = WordlistReader::from_position(path.clone(), 2, Some(6));
+        let mut words = reader.read_words().unwrap();
+        let entry = words.next().unwrap().unwrap();
+        assert!(!words.seeked());
+        assert_eq!(
+            (entry.line, entry.byte_offset, entry.word.as_ref()),
+            (2, 8, &b"three"[..])
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_wordlist_matches_stream() {
+        let path = write_temp(
+            "mmap.txt",
+            b"one\r\n\n  two \nthree\nfour\nf\xfcnf\n$HEX[00ff]\n",
+        );
+        let mut reader = WordlistReader::new(path.clone());
+        let mapped = reader.map().unwrap().unwrap();
+        assert_eq!(mapped.count_lines(), 7);
+
+        let streamed: Vec<WordEntry> = reader.read_words().unwrap().map(|w| w.unwrap()).collect();
+        let borrowed: Vec<WordEntry> = mapped.words_from(0, None).map(|w| w.unwrap()).collect();
+        assert_eq!(streamed, borrowed);
+        assert!(matches!(borrowed[0].word, Cow::Borrowed(b"one")));
+        assert_eq!(borrowed[4].word.as_ref(), b"f\xfcnf");
+        assert_eq!(borrowed[5].word.as_ref(), b"\x00\xff");
+
+        // Resuming by line number alone scans newlines; a byte offset is used as is
+        let words = mapped.words_from(3, None);
+        assert!(!words.seeked());
+        assert_eq!(
+            words.map(|w| w.unwrap().word).collect::<Vec<_>>(),
+            vec![&b"three"[..], b"four", b"f\xfcnf", b"\x00\xff"]
+        );
+        let words = mapped.words_from(3, Some(13));
+        assert!(words.seeked());
+        assert_eq!(
+            words.map(|w| w.unwrap().word).collect::<Vec<_>>(),
+            vec![&b"three"[..], b"four", b"f\xfcnf", b"\x00\xff"]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_mapped_and_streamed_strip_one_cr() {
+        let data = b"pass\r\r\n\r\n\r\r\n \r \r\nlast\r";
+        for options in [
+            LineOptions::default(),
+            LineOptions {
+                trim_whitespace: true,
+                keep_empty: true,
+            },
+        ] {
+            let path = write_temp("cr.txt", data);
+            let mut reader = WordlistReader::new(path.clone()).with_line_options(options);
+            let mapped = reader.map().unwrap().unwrap();
+            let streamed: Vec<WordEntry> =
+                reader.read_words().unwrap().map(|w| w.unwrap()).collect();
+            let borrowed: Vec<WordEntry> =
+                mapped.words_from(0, None).map(|w| w.unwrap()).collect();
+            assert_eq!(streamed, borrowed);
+            if options == LineOptions::default() {
+                let words: Vec<&[u8]> = borrowed.iter().map(|w| w.word.as_ref()).collect();
+                assert_eq!(words, vec![&b"pass\r"[..], b"\r", b" \r ", b"last"]);
+            }
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_line_options() {
+        let path = write_temp("spaces.txt", b" lead\ntrail\t\r\n\n  \nlast ");
+        let words = |options: LineOptions| {
+            let mut reader = WordlistReader::new(path.clone())
+                .with_index(false)
+                .with_line_options(options);
+            let streamed: Vec<Vec<u8>> = reader
+                .read_words()
+                .unwrap()
+                .map(|w| w.unwrap().word.into_owned())
+                .collect();
+            let mapped = reader.map().unwrap().unwrap();
+            let borrowed: Vec<Vec<u8>> = mapped
+                .words_from(0, None)
+                .map(|w| w.unwrap().word.into_owned())
+                .collect();
+            assert_eq!(streamed, borrowed);
+            streamed
+        };
+
+        // Only the line terminator is stripped by default
+        assert_eq!(
+            words(LineOptions::default()),
+            vec![&b" lead"[..], b"trail\t", b"  ", b"last "]
+        );
+        assert_eq!(
+            words(LineOptions {
+                trim_whitespace: true,
+                keep_empty: false,
+            }),
+            vec![&b"lead"[..], b"trail", b"last"]
+        );
+        assert_eq!(
+            words(LineOptions {
+                trim_whitespace: true,
+                keep_empty: true,
+            }),
+            vec![&b"lead"[..], b"trail", b"", b"", b"last"]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_expand_wordlist_directories() {
+        let dir = std::env::temp_dir().join(format!("pbkdf2_cracker_{}_dir", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.txt", "a.txt.gz", "a.txt.gz.idx", ".hidden"] {
+            File::create(dir.join(name)).unwrap();
+        }
+        let single = write_temp("first.txt", b"first\n");
+
+        let dir_arg = dir.to_string_lossy().into_owned();
+        let files = expand_wordlist_paths(&[single.clone(), dir_arg.clone()]).unwrap();
+        let expected = vec![
+            single.clone(),
+            dir.join("a.txt.gz").to_string_lossy().into_owned(),
+            dir.join("b.txt").to_string_lossy().into_owned(),
+        ];
+        assert_eq!(files, expected);
+        assert!(expand_wordlist_paths(&[dir_arg + "/missing"]).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+        std::fs::remove_file(single).unwrap();
+    }
+
+    #[test]
+    fn test_index_offsets_resume_without_full_scan() {
+        let mut data = Vec::new();
+        for i in 0..(INDEX_INTERVAL * 2 + 10) {
+            data.extend_from_slice(format!("w{}\n", i).as_bytes());
         }
+        let path = write_temp("indexed.txt", &data);
+
+        let reader = WordlistReader::new(path.clone());
+        assert!(!reader.has_index());
+        assert_eq!(reader.count_words().unwrap(), INDEX_INTERVAL * 2 + 10);
+        assert!(reader.has_index());
+
+        let mapped = reader.map().unwrap().unwrap();
+        let index = mapped.index.as_ref().unwrap();
+        assert_eq!(index.offsets.len(), 3);
+        assert_eq!(index.offsets, mapped.line_offsets(INDEX_INTERVAL));
+
+        let target = INDEX_INTERVAL * 2 + 3;
+        let first = mapped.words_from(target, None).next().unwrap().unwrap();
+        assert_eq!(
+            (first.line, first.word.as_ref()),
+            (target, format!("w{}", target).as_bytes())
+        );
+
+        let index_path = format!("{}.idx", std::fs::canonicalize(&path).unwrap().display());
+        std::fs::remove_file(index_path).unwrap();
+        std::fs::remove_file(path).unwrap();
     }
 }
@@ -0,0 +1,731 @@
+use crate::encoding::encode_line;
+use crate::errors::{CrackerError, Result};
+use crate::filter::CandidateFilter;
+use crate::wordlist::{expand_wordlist_paths, LineOptions, WordlistReader};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Settings shared by the wordlist preprocessing operations
+///
+/// Lines are read exactly as the cracker reads them (same `LineOptions`,
+/// `$HEX[...]` decoding and filter semantics), so a prepared list yields the
+/// same candidates.
+#[derive(Debug, Clone)]
+pub struct PrepOptions {
+    pub line_options: LineOptions,
+    pub filter: CandidateFilter,
+    /// Memory to use for sorting before spilling runs to disk
+    pub memory_bytes: usize,
+    /// Directory for temporary sort runs
+    pub temp_dir: PathBuf,
+}
+
+impl Default for PrepOptions {
+    fn default() -> Self {
+        Self {
+            line_options: LineOptions::default(),
+            filter: CandidateFilter::default(),
+            memory_bytes: 256 * 1024 * 1024,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// Word counts reported by a preprocessing run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrepStats {
+    /// Words read from the inputs
+    pub read: u64,
+    /// Words rejected by the filter
+    pub filtered: u64,
+    /// Words written to the output(s)
+    pub written: u64,
+}
+
+/// Concatenate wordlists in order, applying the filter
+pub fn merge(inputs: &[String], output: &str, options: &PrepOptions) -> Result<PrepStats> {
+    let files = input_files(inputs, &[output.to_string()])?;
+    let mut stats = PrepStats::default();
+    let mut writer = WordWriter::create(output)?;
+    for_each_word(&files, options, &mut stats, |word| writer.write(&word))?;
+    stats.written = writer.finish()?;
+    Ok(stats)
+}
+
+/// Remove duplicate words, keeping the first occurrence of each in order
+pub fn dedup(inputs: &[String], output: &str, options: &PrepOptions) -> Result<PrepStats> {
+    unique_words(inputs, output, options, Order::FirstSeen)
+}
+
+/// Remove duplicate words and order them by frequency, most common first
+/// Ties keep first-seen order.
+pub fn sort_by_frequency(
+    inputs: &[String],
+    output: &str,
+    options: &PrepOptions,
+) -> Result<PrepStats> {
+    unique_words(inputs, output, options, Order::Frequency)
+}
+
+/// Path of shard `index` (zero-based) of a split
+pub fn shard_path(prefix: &str, index: usize) -> String {
+    format!("{}.{}", prefix, index + 1)
+}
+
+/// Deal words round-robin into `shards` files named `<prefix>.1` .. `<prefix>.N`
+///
+/// Round-robin keeps every shard a representative slice of the input, so a
+/// frequency-sorted list stays frequency-sorted within each shard.
+pub fn split(
+    inputs: &[String],
+    prefix: &str,
+    shards: usize,
+    options: &PrepOptions,
+) -> Result<PrepStats> {
+    if shards == 0 {
+        return Err(CrackerError::WordlistWriteError(
+            "Shard count must be greater than 0".to_string(),
+        ));
+    }
+
+    let shard_paths: Vec<String> = (0..shards).map(|i| shard_path(prefix, i)).collect();
+    let files = input_files(inputs, &shard_paths)?;
+    let mut writers = shard_paths
+        .iter()
+        .map(|path| WordWriter::create(path))
+        .collect::<Result<Vec<_>>>()?;
+    let mut next = 0;
+    let mut stats = PrepStats::default();
+    for_each_word(&files, options, &mut stats, |word| {
+        writers[next].write(&word)?;
+        next = (next + 1) % shards;
+        Ok(())
+    })?;
+    for writer in writers {
+        stats.written += writer.finish()?;
+    }
+    Ok(stats)
+}
+
+/// Files to read for `inputs`, expanded before any output is created
+///
+/// Outputs and their temp files found in an input directory are left out,
+/// so a run never reads what it writes; an output named as an input
+/// itself is read, and replaced once the run is done.
+fn input_files(inputs: &[String], outputs: &[String]) -> Result<Vec<String>> {
+    let canonical = |path: &str| std::fs::canonicalize(path).ok();
+    let named: Vec<PathBuf> = inputs.iter().filter_map(|path| canonical(path)).collect();
+    let excluded: Vec<PathBuf> = outputs
+        .iter()
+        .flat_map(|output| [output.clone(), format!("{}.tmp", output)])
+        .filter_map(|path| canonical(&path))
+        .filter(|path| !named.contains(path))
+        .collect();
+
+    let files: Vec<String> = expand_wordlist_paths(inputs)?
+        .into_iter()
+        .filter(|file| canonical(file).is_none_or(|path| !excluded.contains(&path)))
+        .collect();
+    if files.is_empty() {
+        return Err(CrackerError::WordlistNotFound(format!(
+            "No wordlist files in {} besides the output",
+            inputs.join(", ")
+        )));
+    }
+    Ok(files)
+}
+
+/// Read every word of `files` that passes the filter
+fn for_each_word<F>(
+    files: &[String],
+    options: &PrepOptions,
+    stats: &mut PrepStats,
+    mut f: F,
+) -> Result<()>
+where
+    F: FnMut(Vec<u8>) -> Result<()>,
+{
+    for path in files {
+        let mut reader = WordlistReader::new(path.clone())
+            .with_index(false)
+            .with_line_options(options.line_options);
+        for entry in reader.read_words()? {
+            let word = entry?.word.into_owned();
+            stats.read += 1;
+            if !options.filter.allows(&word) {
+                stats.filtered += 1;
+                continue;
+            }
+            f(word)?;
+        }
+    }
+    Ok(())
+}
+
+/// Two-pass external dedup
+///
+/// The first sort groups equal words to find each word's first position and
+/// count; the second restores the requested order. Both passes spill sorted
+/// runs to disk when they exceed half the memory budget.
+fn unique_words(
+    inputs: &[String],
+    output: &str,
+    options: &PrepOptions,
+    order: Order,
+) -> Result<PrepStats> {
+    let files = input_files(inputs, &[output.to_string()])?;
+    let temp = TempDir::create(&options.temp_dir)?;
+    let budget = options.memory_bytes / 2;
+
+    let mut stats = PrepStats::default();
+    let mut by_word = ExternalSort::new(Order::Word, temp.path.join("word"), budget);
+    let mut position = 0;
+    for_each_word(&files, options, &mut stats, |word| {
+        position += 1;
+        by_word.push(Record {
+            word,
+            first_seen: position,
+            count: 1,
+        })
+    })?;
+
+    let mut by_order = ExternalSort::new(order, temp.path.join("order"), budget);
+    let mut current: Option<Record> = None;
+    for record in by_word.finish()? {
+        let record = record?;
+        if let Some(group) = current.as_mut().filter(|c| c.word == record.word) {
+            group.absorb(record);
+            continue;
+        }
+        if let Some(done) = current.replace(record) {
+            by_order.push(done)?;
+        }
+    }
+    if let Some(done) = current {
+        by_order.push(done)?;
+    }
+
+    let mut writer = WordWriter::create(output)?;
+    for record in by_order.finish()? {
+        writer.write(&record?.word)?;
+    }
+    stats.written = writer.finish()?;
+    Ok(stats)
+}
+
+/// Buffered wordlist output
+///
+/// Words go to `<path>.tmp`, which replaces `path` only in `finish`, so an
+/// output that is also an input is read in full before it is overwritten.
+struct WordWriter {
+    path: String,
+    temp_path: String,
+    writer: BufWriter<File>,
+    written: u64,
+    finished: bool,
+}
+
+impl WordWriter {
+    fn create(path: &str) -> Result<Self> {
+        let temp_path = format!("{}.tmp", path);
+        let file = File::create(&temp_path).map_err(|e| {
+            CrackerError::WordlistWriteError(format!("Failed to create {}: {}", temp_path, e))
+        })?;
+        Ok(Self {
+            path: path.to_string(),
+            temp_path,
+            writer: BufWriter::new(file),
+            written: 0,
+            finished: false,
+        })
+    }
+
+    fn write(&mut self, word: &[u8]) -> Result<()> {
+        self.writer
+            .write_all(&encode_line(word))
+            .and_then(|_| self.writer.write_all(b"\n"))
+            .map_err(|e| {
+                CrackerError::WordlistWriteError(format!("Failed to write {}: {}", self.path, e))
+            })?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flush the output, move it into place and return the number of words written
+    fn finish(mut self) -> Result<u64> {
+        self.writer.flush().map_err(|e| {
+            CrackerError::WordlistWriteError(format!("Failed to write {}: {}", self.path, e))
+        })?;
+        std::fs::rename(&self.temp_path, &self.path).map_err(|e| {
+            CrackerError::WordlistWriteError(format!("Failed to write {}: {}", self.path, e))
+        })?;
+        self.finished = true;
+        Ok(self.written)
+    }
+}
+
+impl Drop for WordWriter {
+    fn drop(&mut self) {
+        // A failed run leaves the previous output untouched
+        if !self.finished {
+            let _ = std::fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// A word with its first position in the input and its number of occurrences
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Record {
+    word: Vec<u8>,
+    first_seen: u64,
+    count: u64,
+}
+
+impl Record {
+    /// Merge another occurrence group of the same word into this one
+    fn absorb(&mut self, other: Record) {
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.count += other.count;
+    }
+
+    /// Approximate heap footprint, for the memory budget
+    fn footprint(&self) -> usize {
+        self.word.capacity() + std::mem::size_of::<Record>()
+    }
+}
+
+/// Sort order of an external sort pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Order {
+    /// By word bytes, then first position
+    Word,
+    /// By first position
+    FirstSeen,
+    /// By count descending, then first position
+    Frequency,
+}
+
+impl Order {
+    fn compare(self, a: &Record, b: &Record) -> Ordering {
+        match self {
+            Order::Word => a.word.cmp(&b.word).then(a.first_seen.cmp(&b.first_seen)),
+            Order::FirstSeen => a.first_seen.cmp(&b.first_seen),
+            Order::Frequency => b.count.cmp(&a.count).then(a.first_seen.cmp(&b.first_seen)),
+        }
+    }
+}
+
+/// Most runs open at once in a merge; more are merged in passes
+const MAX_MERGE_RUNS: usize = 64;
+
+/// Sort that keeps at most `budget` bytes of records in memory
+///
+/// Full buffers are sorted and written to numbered run files under `prefix`;
+/// `finish` merges the runs back with a heap, at most `MAX_MERGE_RUNS` at a
+/// time, through intermediate runs when there are more.
+struct ExternalSort {
+    order: Order,
+    prefix: PathBuf,
+    budget: usize,
+    buffer: Vec<Record>,
+    used: usize,
+    runs: Vec<PathBuf>,
+    next_run: usize,
+}
+
+impl ExternalSort {
+    fn new(order: Order, prefix: PathBuf, budget: usize) -> Self {
+        Self {
+            order,
+            prefix,
+            budget,
+            buffer: Vec::new(),
+            used: 0,
+            runs: Vec::new(),
+            next_run: 0,
+        }
+    }
+
+    fn push(&mut self, record: Record) -> Result<()> {
+        self.used += record.footprint();
+        self.buffer.push(record);
+        if self.used >= self.budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn sort_buffer(&mut self) {
+        let order = self.order;
+        self.buffer.sort_unstable_by(|a, b| order.compare(a, b));
+        if order == Order::Word {
+            // Collapse repeats within the run so runs stay small
+            self.buffer.dedup_by(|later, group| {
+                if later.word != group.word {
+                    return false;
+                }
+                group.count += later.count;
+                true
+            });
+        }
+    }
+
+    fn next_run_path(&mut self) -> PathBuf {
+        self.next_run += 1;
+        PathBuf::from(format!("{}.{}", self.prefix.display(), self.next_run))
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        self.sort_buffer();
+        let path = self.next_run_path();
+        write_run(&path, self.buffer.drain(..).map(Ok))?;
+        self.runs.push(path);
+        self.used = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<SortedRecords> {
+        if self.runs.is_empty() {
+            self.sort_buffer();
+            return Ok(SortedRecords::Memory(self.buffer.into_iter()));
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+
+        while self.runs.len() > MAX_MERGE_RUNS {
+            let runs = std::mem::take(&mut self.runs);
+            for group in runs.chunks(MAX_MERGE_RUNS) {
+                let path = self.next_run_path();
+                write_run(&path, open_runs(self.order, group)?)?;
+                for run in group {
+                    let _ = std::fs::remove_file(run);
+                }
+                self.runs.push(path);
+            }
+        }
+        open_runs(self.order, &self.runs)
+    }
+}
+
+/// Write records to a new run file
+fn write_run(path: &Path, records: impl Iterator<Item = Result<Record>>) -> Result<()> {
+    let write_error = |e: std::io::Error| {
+        CrackerError::WordlistWriteError(format!("Failed to write sort run: {}", e))
+    };
+    let file = File::create(path).map_err(|e| {
+        CrackerError::WordlistWriteError(format!("Failed to create sort run: {}", e))
+    })?;
+    let mut writer = BufWriter::new(file);
+    for record in records {
+        write_record(&mut writer, &record?).map_err(write_error)?;
+    }
+    writer.flush().map_err(write_error)
+}
+
+/// Merge sorted run files with a heap
+fn open_runs(order: Order, runs: &[PathBuf]) -> Result<SortedRecords> {
+    let mut readers = Vec::with_capacity(runs.len());
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (source, path) in runs.iter().enumerate() {
+        let file = File::open(path).map_err(|e| {
+            CrackerError::WordlistReadError(format!("Failed to open sort run: {}", e))
+        })?;
+        let mut reader = BufReader::new(file);
+        if let Some(record) = read_record(&mut reader)? {
+            heap.push(HeapEntry {
+                record,
+                source,
+                order,
+            });
+        }
+        readers.push(reader);
+    }
+    Ok(SortedRecords::Runs { readers, heap })
+}
+
+/// Records of a finished sort, in order
+enum SortedRecords {
+    Memory(std::vec::IntoIter<Record>),
+    Runs {
+        readers: Vec<BufReader<File>>,
+        heap: BinaryHeap<HeapEntry>,
+    },
+}
+
+impl Iterator for SortedRecords {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortedRecords::Memory(records) => records.next().map(Ok),
+            SortedRecords::Runs { readers, heap } => {
+                let entry = heap.pop()?;
+                match read_record(&mut readers[entry.source]) {
+                    Ok(Some(record)) => heap.push(HeapEntry { record, ..entry }),
+                    Ok(None) => {}
+                    Err(e) => return Some(Err(e)),
+                }
+                Some(Ok(entry.record))
+            }
+        }
+    }
+}
+
+/// Heap entry for the k-way merge; reversed so the heap pops the smallest
+struct HeapEntry {
+    record: Record,
+    source: usize,
+    order: Order,
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order
+            .compare(&other.record, &self.record)
+            .then(other.source.cmp(&self.source))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+/// Run file record: word length (u32 LE), word bytes, first position and count (u64 LE)
+fn write_record(writer: &mut impl Write, record: &Record) -> std::io::Result<()> {
+    writer.write_all(&(record.word.len() as u32).to_le_bytes())?;
+    writer.write_all(&record.word)?;
+    writer.write_all(&record.first_seen.to_le_bytes())?;
+    writer.write_all(&record.count.to_le_bytes())
+}
+
+fn read_record(reader: &mut BufReader<File>) -> Result<Option<Record>> {
+    let read = |reader: &mut BufReader<File>| -> std::io::Result<Option<Record>> {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let mut u32_buf = [0u8; 4];
+        let mut u64_buf = [0u8; 8];
+        reader.read_exact(&mut u32_buf)?;
+        let mut word = vec![0u8; u32::from_le_bytes(u32_buf) as usize];
+        reader.read_exact(&mut word)?;
+        reader.read_exact(&mut u64_buf)?;
+        let first_seen = u64::from_le_bytes(u64_buf);
+        reader.read_exact(&mut u64_buf)?;
+        let count = u64::from_le_bytes(u64_buf);
+        Ok(Some(Record {
+            word,
+            first_seen,
+            count,
+        }))
+    };
+    read(reader)
+        .map_err(|e| CrackerError::WordlistReadError(format!("Failed to read sort run: {}", e)))
+}
+
+/// Scratch directory for sort runs, removed when dropped
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn create(parent: &Path) -> Result<Self> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let path = parent.join(format!(
+            "pbkdf2_cracker_sort_{}_{}",
+            std::process::id(),
+            nanos
+        ));
+        std::fs::create_dir_all(&path).map_err(|e| {
+            CrackerError::WordlistWriteError(format!(
+                "Failed to create temporary directory {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::CharClass;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!(
+                "pbkdf2_cracker_prep_{}_{}",
+                std::process::id(),
+                name
+            ))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn write_list(name: &str, data: &[u8]) -> String {
+        let path = temp_path(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn read_list(path: &str) -> Vec<u8> {
+        let data = std::fs::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_dedup_and_frequency_sort_spill_to_disk() {
+        let first = write_list("first.txt", b"b\na\nc\na\n$HEX[0a]\n");
+        let second = write_list("second.txt", b"c\nd\na\n\n");
+        let inputs = [first.clone(), second.clone()];
+
+        // A budget of a single byte spills every record to its own run
+        for memory_bytes in [1, 1 << 20] {
+            let options = PrepOptions {
+                memory_bytes,
+                ..PrepOptions::default()
+            };
+
+            let output = temp_path("dedup.txt");
+            let stats = dedup(&inputs, &output, &options).unwrap();
+            assert_eq!(read_list(&output), b"b\na\nc\n$HEX[0a]\nd\n");
+            assert_eq!((stats.read, stats.written), (8, 5));
+
+            let output = temp_path("sorted.txt");
+            sort_by_frequency(&inputs, &output, &options).unwrap();
+            assert_eq!(read_list(&output), b"a\nc\nb\n$HEX[0a]\nd\n");
+        }
+
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn test_many_runs_merge_in_passes() {
+        let numbered = |range: &mut dyn Iterator<Item = u32>| -> Vec<u8> {
+            range
+                .flat_map(|i| format!("{}\n", i).into_bytes())
+                .collect()
+        };
+        let mut contents = numbered(&mut (0..200).rev());
+        contents.extend(numbered(&mut (0..100)));
+        let input = write_list("many.txt", &contents);
+        let inputs = [input.clone()];
+
+        // 300 single-record runs take more than one pass of MAX_MERGE_RUNS
+        let options = PrepOptions {
+            memory_bytes: 1,
+            ..PrepOptions::default()
+        };
+
+        let output = temp_path("many_dedup.txt");
+        dedup(&inputs, &output, &options).unwrap();
+        assert_eq!(read_list(&output), numbered(&mut (0..200).rev()));
+
+        let output = temp_path("many_sorted.txt");
+        sort_by_frequency(&inputs, &output, &options).unwrap();
+        let mut expected = numbered(&mut (0..100).rev());
+        expected.extend(numbered(&mut (100..200).rev()));
+        assert_eq!(read_list(&output), expected);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn test_merge_filter_and_split() {
+        let input = write_list("input.txt", b" pass1\nword\nPassword1\nabc123\nqwerty9\n");
+        let inputs = [input.clone()];
+        let options = PrepOptions {
+            filter: CandidateFilter::new(Some(6), None, vec![CharClass::Digit]),
+            ..PrepOptions::default()
+        };
+
+        let output = temp_path("merged.txt");
+        let stats = merge(&inputs, &output, &options).unwrap();
+        assert_eq!(read_list(&output), b" pass1\nPassword1\nabc123\nqwerty9\n");
+        assert_eq!((stats.read, stats.filtered, stats.written), (5, 1, 4));
+
+        let prefix = temp_path("shard");
+        let stats = split(&inputs, &prefix, 3, &options).unwrap();
+        assert_eq!(stats.written, 4);
+        assert_eq!(read_list(&shard_path(&prefix, 0)), b" pass1\nqwerty9\n");
+        assert_eq!(read_list(&shard_path(&prefix, 1)), b"Password1\n");
+        assert_eq!(read_list(&shard_path(&prefix, 2)), b"abc123\n");
+        assert!(split(&inputs, &prefix, 0, &options).is_err());
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn test_output_may_be_an_input() {
+        let list = write_list("in_place.txt", b"b\na\nb\nc\n");
+        let inputs = [list.clone()];
+        let options = PrepOptions::default();
+
+        let stats = merge(&inputs, &list, &options).unwrap();
+        assert_eq!((stats.read, stats.written), (4, 4));
+        assert_eq!(std::fs::read(&list).unwrap(), b"b\na\nb\nc\n");
+
+        dedup(&inputs, &list, &options).unwrap();
+        assert_eq!(std::fs::read(&list).unwrap(), b"b\na\nc\n");
+
+        // A failed run leaves neither a truncated output nor a temp file
+        let missing = [temp_path("missing.txt")];
+        assert!(merge(&missing, &list, &options).is_err());
+        assert_eq!(read_list(&list), b"b\na\nc\n");
+        assert!(!Path::new(&format!("{}.tmp", list)).exists());
+    }
+
+    #[test]
+    fn test_output_in_input_directory() {
+        let dir = temp_path("lists");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(Path::new(&dir).join("a.txt"), b"a\nb\n").unwrap();
+        std::fs::write(Path::new(&dir).join("b.txt"), b"c\n").unwrap();
+        let inputs = [dir.clone()];
+        let output = Path::new(&dir).join("z.txt").to_string_lossy().into_owned();
+        let options = PrepOptions::default();
+
+        // Neither the output being written nor an earlier one is read
+        for _ in 0..2 {
+            let stats = merge(&inputs, &output, &options).unwrap();
+            assert_eq!((stats.read, stats.written), (3, 3));
+            assert_eq!(std::fs::read(&output).unwrap(), b"a\nb\nc\n");
+        }
+        dedup(&inputs, &output, &options).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), b"a\nb\nc\n");
+        std::fs::remove_file(&output).unwrap();
+
+        let prefix = Path::new(&dir).join("shard").to_string_lossy().into_owned();
+        for _ in 0..2 {
+            let stats = split(&inputs, &prefix, 2, &options).unwrap();
+            assert_eq!(stats.read, 3);
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}